@@ -0,0 +1,63 @@
+//! Reports parse time and peak heap use for a recorded catalog:
+//!
+//! `BREWDECK_CATALOG=formula.json cargo bench --bench parse_catalog`
+//!
+//! Falls back to the small fixture, which only proves the harness works.
+
+use brew_deck_lib::bench::{HostPlatform, PackageParser, PackageType};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const FORMULA_FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/formula.json");
+
+/// Counts live heap bytes so the peak during parsing can be reported
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK_ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let pointer = System.alloc(layout);
+        if !pointer.is_null() {
+            let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK_ALLOCATED.fetch_max(allocated, Ordering::Relaxed);
+        }
+        pointer
+    }
+
+    unsafe fn dealloc(&self, pointer: *mut u8, layout: Layout) {
+        System.dealloc(pointer, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn main() {
+    let path = std::env::var("BREWDECK_CATALOG").unwrap_or_else(|_| FORMULA_FIXTURE.to_string());
+    let package_type = if path.contains("cask") { PackageType::Cask } else { PackageType::Formula };
+    let file = std::fs::File::open(&path).unwrap_or_else(|e| panic!("Cannot open {path}: {e}"));
+    let bytes = file.metadata().unwrap().len();
+
+    let baseline = ALLOCATED.load(Ordering::Relaxed);
+    PEAK_ALLOCATED.store(baseline, Ordering::Relaxed);
+    let start_time = std::time::Instant::now();
+
+    let infos = PackageParser::parse_catalog(package_type, std::io::BufReader::new(file), HostPlatform::current())
+        .expect("catalog should parse");
+
+    let elapsed = start_time.elapsed();
+    let peak = PEAK_ALLOCATED.load(Ordering::Relaxed).saturating_sub(baseline);
+    let retained = ALLOCATED.load(Ordering::Relaxed).saturating_sub(baseline);
+    println!(
+        "Parsed {} {} records from {} bytes in {:?}: peak heap {} bytes, {} bytes retained",
+        infos.len(),
+        package_type,
+        bytes,
+        elapsed,
+        peak,
+        retained
+    );
+}
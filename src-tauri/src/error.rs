@@ -22,6 +22,11 @@ pub enum BrewDeckError {
     #[error("Network error: {0}")]
     NetworkError(String),
     
+    /// The message and the status the server answered with; the server was
+    /// reachable, so unlike `NetworkError` this does not mean we are offline
+    #[error("HTTP error: {0}")]
+    HttpStatusError(String, u16),
+    
     #[error("Cache error: {0}")]
     CacheError(String),
     
@@ -43,16 +48,37 @@ pub enum BrewDeckError {
     #[error("Timeout error: {0}")]
     TimeoutError(String),
     
+    /// The message and, when the server sent `Retry-After`, the seconds to wait
     #[error("Rate limit exceeded: {0}")]
-    RateLimitExceeded(String),
+    RateLimitExceeded(String, Option<u64>),
     
     #[error("Invalid configuration: {0}")]
     InvalidConfiguration(String),
     
+    #[error("Signature verification failed: {0}")]
+    SignatureVerificationFailed(String),
+    
+    #[error("Unsupported platform: {0}")]
+    UnsupportedPlatform(String),
+    
+    /// The message and the character offset in the query it refers to
+    #[error("Invalid query at position {1}: {0}")]
+    QuerySyntaxError(String, usize),
+    
     #[error("Internal error: {0}")]
     InternalError(String),
 }
 
+impl BrewDeckError {
+    /// How long the server asked us to wait before trying again
+    pub fn retry_after(&self) -> Option<std::time::Duration> {
+        match self {
+            BrewDeckError::RateLimitExceeded(_, Some(seconds)) => Some(std::time::Duration::from_secs(*seconds)),
+            _ => None,
+        }
+    }
+}
+
 impl From<reqwest::Error> for BrewDeckError {
     fn from(err: reqwest::Error) -> Self {
         if err.is_timeout() {
@@ -62,9 +88,9 @@ impl From<reqwest::Error> for BrewDeckError {
         } else if err.is_status() {
             if let Some(status) = err.status() {
                 if status == 429 {
-                    BrewDeckError::RateLimitExceeded(err.to_string())
+                    BrewDeckError::RateLimitExceeded(err.to_string(), None)
                 } else {
-                    BrewDeckError::NetworkError(format!("HTTP {status}: {err}"))
+                    BrewDeckError::HttpStatusError(err.to_string(), status.as_u16())
                 }
             } else {
                 BrewDeckError::NetworkError(err.to_string())
@@ -179,6 +205,10 @@ impl<T> BrewDeckResult<T> {
     }
 }
 
+/// Longest `Retry-After` worth waiting out inside a single operation; beyond
+/// that the error is returned so the caller can fall back instead of hanging
+pub(crate) const MAX_RETRY_AFTER: std::time::Duration = std::time::Duration::from_secs(60);
+
 /// Retry mechanism for operations
 pub async fn retry_with_backoff<F, Fut, T>(
     mut operation: F,
@@ -196,7 +226,12 @@ where
                     return Err(error);
                 }
                 
-                let backoff = recovery.get_backoff_duration();
+                // A server-provided delay replaces the exponential backoff
+                let backoff = match error.retry_after() {
+                    Some(retry_after) if retry_after > MAX_RETRY_AFTER => return Err(error),
+                    Some(retry_after) => retry_after,
+                    None => recovery.get_backoff_duration(),
+                };
                 recovery.increment_retry();
                 
                 tracing::warn!(
@@ -1,22 +1,42 @@
 mod error;
 mod services;
 
+/// Parser internals for `benches/parse_catalog.rs`; not a stable API
+#[doc(hidden)]
+pub mod bench {
+    pub use crate::services::api_client::PackageParser;
+    pub use crate::services::brew_client::PackageType;
+    pub use crate::services::platform::HostPlatform;
+}
+
 use error::BrewDeckError;
-use services::brew_client::PackageType;
+use services::api_client::ApiEndpointConfig;
+use services::brew_client::{BrewEnvironment, PackageType, UpdateSummary};
 use services::cache_manager::CacheConfig;
-use services::package_service::BrewPackage;
+use services::catalog_store::CatalogStatus;
+use services::connectivity::{CatalogFreshness, OfflineStatus};
+use services::disk_usage::DiskUsageSummary;
+use services::http_client::HttpSettings;
+use services::license::{LicensePolicy, LicenseReport};
+use services::package_service::{BrewPackage, CaskOutdatedReport, CaskUpgradeSettings, PackageWarning, UnifiedSearchResult};
+use services::package_query::{PackagePage, PackageQuery};
+use services::platform::PlatformCapabilities;
 use services::prefetch_service::{PrefetchConfig, PrefetchStats, NetworkConditions};
-use services::{BrewClient, CacheManager, PackageService, PrefetchService};
+use services::signature::SignatureConfig;
+use services::{CacheManager, PackageService, PrefetchService};
 
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use tauri::Emitter;
 use tracing_subscriber;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BrewInfo {
-    pub packages: Vec<BrewPackage>,
+    pub packages: Arc<Vec<BrewPackage>>,
     pub total_installed: usize,
     pub total_outdated: usize,
+    /// Marks data served from the last good snapshot while offline
+    pub freshness: Option<CatalogFreshness>,
 }
 
 // Global service instances
@@ -71,9 +91,23 @@ async fn get_brew_info() -> Result<BrewInfo, String> {
         packages,
         total_installed,
         total_outdated,
+        freshness: service.catalog_freshness(PackageType::Formula).await,
     })
 }
 
+#[tauri::command]
+async fn query_packages(package_type: String, query: PackageQuery) -> Result<PackagePage, String> {
+    let service = get_package_service().await.map_err(|e| e.to_string())?;
+
+    let pkg_type = package_type
+        .parse::<PackageType>()
+        .map_err(|e| e.to_string())?;
+    service
+        .query_packages(pkg_type, &query)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 async fn install_package(package_name: String) -> Result<String, String> {
     let service = get_package_service().await.map_err(|e| e.to_string())?;
@@ -124,17 +158,24 @@ async fn update_package(package_name: String) -> Result<String, String> {
 
 #[tauri::command]
 async fn update_all_packages() -> Result<String, String> {
-    let _service = get_package_service().await.map_err(|e| e.to_string())?;
+    let service = get_package_service().await.map_err(|e| e.to_string())?;
 
-    // For now, we'll use the brew client directly for update all
-    // In a full implementation, this would be handled by the service
-    let brew_client = BrewClient::new().await.map_err(|e| e.to_string())?;
-    let result = brew_client
-        .update_all(Some(PackageType::Formula))
+    service
+        .update_all_packages(PackageType::Formula)
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(|e| e.to_string())
+}
 
-    Ok(result)
+#[tauri::command]
+async fn update_homebrew() -> Result<UpdateSummary, String> {
+    let service = get_package_service().await.map_err(|e| e.to_string())?;
+    service.update_homebrew().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_last_update_summary() -> Result<Option<UpdateSummary>, String> {
+    let service = get_package_service().await.map_err(|e| e.to_string())?;
+    Ok(service.last_update_summary().await)
 }
 
 #[tauri::command]
@@ -169,6 +210,7 @@ async fn get_cask_info() -> Result<BrewInfo, String> {
         packages,
         total_installed,
         total_outdated,
+        freshness: service.catalog_freshness(PackageType::Cask).await,
     })
 }
 
@@ -184,6 +226,19 @@ async fn search_casks(query: String) -> Result<Vec<BrewPackage>, String> {
     Ok(result.packages)
 }
 
+#[tauri::command]
+async fn search_all_packages(query: String) -> Result<UnifiedSearchResult, String> {
+    let service = get_package_service().await.map_err(|e| e.to_string())?;
+    service.search_all_packages(&query).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn advanced_search(query: String) -> Result<UnifiedSearchResult, String> {
+    let service = get_package_service().await.map_err(|e| e.to_string())?;
+    // Syntax errors read "Invalid query at position N: ..."
+    service.advanced_search(&query).await.map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 async fn install_cask(package_name: String) -> Result<String, String> {
     let service = get_package_service().await.map_err(|e| e.to_string())?;
@@ -234,13 +289,30 @@ async fn update_cask(package_name: String) -> Result<String, String> {
 
 #[tauri::command]
 async fn update_all_casks() -> Result<String, String> {
-    let brew_client = BrewClient::new().await.map_err(|e| e.to_string())?;
-    let result = brew_client
-        .update_all(Some(PackageType::Cask))
+    let service = get_package_service().await.map_err(|e| e.to_string())?;
+
+    service
+        .update_all_packages(PackageType::Cask)
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_cask_upgrade_settings() -> Result<CaskUpgradeSettings, String> {
+    let service = get_package_service().await.map_err(|e| e.to_string())?;
+    Ok(service.get_cask_upgrade_settings().await)
+}
 
-    Ok(result)
+#[tauri::command]
+async fn update_cask_upgrade_settings(settings: CaskUpgradeSettings) -> Result<(), String> {
+    let service = get_package_service().await.map_err(|e| e.to_string())?;
+    service.update_cask_upgrade_settings(settings).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_cask_outdated_report() -> Result<CaskOutdatedReport, String> {
+    let service = get_package_service().await.map_err(|e| e.to_string())?;
+    service.get_cask_outdated_report().await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -261,6 +333,112 @@ async fn get_package_details(
     Ok(package)
 }
 
+#[tauri::command]
+async fn get_install_warnings(
+    package_name: String,
+    package_type: String,
+) -> Result<Vec<PackageWarning>, String> {
+    let service = get_package_service().await.map_err(|e| e.to_string())?;
+
+    let pkg_type = package_type
+        .parse::<PackageType>()
+        .map_err(|e| e.to_string())?;
+    service
+        .install_warnings(&package_name, pkg_type)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_disk_usage_summary() -> Result<DiskUsageSummary, String> {
+    let service = get_package_service().await.map_err(|e| e.to_string())?;
+    service.get_disk_usage_summary().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_api_endpoints() -> Result<ApiEndpointConfig, String> {
+    let service = get_package_service().await.map_err(|e| e.to_string())?;
+    Ok(service.get_api_endpoints().await)
+}
+
+#[tauri::command]
+async fn set_api_endpoints(endpoints: ApiEndpointConfig) -> Result<(), String> {
+    let service = get_package_service().await.map_err(|e| e.to_string())?;
+    service.set_api_endpoints(endpoints).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_http_settings() -> Result<HttpSettings, String> {
+    let service = get_package_service().await.map_err(|e| e.to_string())?;
+    Ok(service.get_http_settings().await)
+}
+
+#[tauri::command]
+async fn set_http_settings(settings: HttpSettings) -> Result<(), String> {
+    let service = get_package_service().await.map_err(|e| e.to_string())?;
+    service.set_http_settings(settings).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_license_policy() -> Result<LicensePolicy, String> {
+    let service = get_package_service().await.map_err(|e| e.to_string())?;
+    Ok(service.get_license_policy().await)
+}
+
+#[tauri::command]
+async fn set_license_policy(policy: LicensePolicy) -> Result<(), String> {
+    let service = get_package_service().await.map_err(|e| e.to_string())?;
+    service.set_license_policy(policy).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_license_report() -> Result<LicenseReport, String> {
+    let service = get_package_service().await.map_err(|e| e.to_string())?;
+    service.get_license_report().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_signature_config() -> Result<SignatureConfig, String> {
+    let service = get_package_service().await.map_err(|e| e.to_string())?;
+    Ok(service.get_signature_config().await)
+}
+
+#[tauri::command]
+async fn set_signature_config(config: SignatureConfig) -> Result<(), String> {
+    let service = get_package_service().await.map_err(|e| e.to_string())?;
+    service.set_signature_config(config).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_catalog_status() -> Result<Vec<CatalogStatus>, String> {
+    let service = get_package_service().await.map_err(|e| e.to_string())?;
+    Ok(service.get_catalog_status().await)
+}
+
+#[tauri::command]
+async fn get_offline_status() -> Result<OfflineStatus, String> {
+    let service = get_package_service().await.map_err(|e| e.to_string())?;
+    Ok(service.get_offline_status().await)
+}
+
+#[tauri::command]
+async fn set_offline_mode(offline: bool) -> Result<OfflineStatus, String> {
+    let service = get_package_service().await.map_err(|e| e.to_string())?;
+    Ok(service.set_offline_mode(offline).await)
+}
+
+#[tauri::command]
+async fn get_platform_capabilities() -> Result<PlatformCapabilities, String> {
+    let service = get_package_service().await.map_err(|e| e.to_string())?;
+    Ok(service.get_platform_capabilities())
+}
+
+#[tauri::command]
+async fn get_brew_environment() -> Result<BrewEnvironment, String> {
+    let service = get_package_service().await.map_err(|e| e.to_string())?;
+    service.get_brew_environment().await.map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 async fn update_prefetch_config(config: PrefetchConfig) -> Result<(), String> {
     let service = get_prefetch_service().await.map_err(|e| e.to_string())?;
@@ -309,21 +487,81 @@ fn greet(name: &str) -> String {
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
+        .setup(|app| {
+            // Refresh the UI when packages change outside BrewDeck
+            let app_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                let service = match get_package_service().await {
+                    Ok(service) => service,
+                    Err(e) => {
+                        tracing::warn!("Package watcher not started: {}", e);
+                        return;
+                    }
+                };
+
+                let watcher_handle = app_handle.clone();
+                let result = service
+                    .start_watching(move |event| {
+                        if let Err(e) = watcher_handle.emit("packages-changed", event) {
+                            tracing::warn!("Failed to emit packages-changed event: {}", e);
+                        }
+                    })
+                    .await;
+
+                if let Err(e) = result {
+                    tracing::warn!("Package watcher not started: {}", e);
+                }
+
+                // Tell the UI when BrewDeck goes offline or comes back
+                service
+                    .monitor_connectivity(std::time::Duration::from_secs(30), move |status| {
+                        if let Err(e) = app_handle.emit("connectivity-changed", status) {
+                            tracing::warn!("Failed to emit connectivity-changed event: {}", e);
+                        }
+                    })
+                    .await;
+            });
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             greet,
             get_brew_info,
+            query_packages,
             install_package,
             uninstall_package,
             update_package,
             update_all_packages,
+            update_homebrew,
+            get_last_update_summary,
             search_packages,
             get_cask_info,
             search_casks,
+            search_all_packages,
+            advanced_search,
             install_cask,
             uninstall_cask,
             update_cask,
             update_all_casks,
+            get_cask_upgrade_settings,
+            update_cask_upgrade_settings,
+            get_cask_outdated_report,
             get_package_details,
+            get_install_warnings,
+            get_platform_capabilities,
+            get_brew_environment,
+            get_disk_usage_summary,
+            get_api_endpoints,
+            set_api_endpoints,
+            get_http_settings,
+            set_http_settings,
+            get_license_policy,
+            set_license_policy,
+            get_license_report,
+            get_signature_config,
+            set_signature_config,
+            get_catalog_status,
+            get_offline_status,
+            set_offline_mode,
             update_prefetch_config,
             update_network_conditions,
             get_prefetch_stats,
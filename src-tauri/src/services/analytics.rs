@@ -0,0 +1,329 @@
+use crate::error::BrewDeckError;
+use crate::services::api_client::{ApiAnalytics, ApiAnalyticsItem, ApiClient};
+use crate::services::brew_client::PackageType;
+use crate::services::package_service::{AnalyticsCounts, PackageAnalytics};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use tracing::{debug, info, warn};
+
+/// formulae.brew.sh regenerates analytics once a day
+const ANALYTICS_REFRESH_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnalyticsCategory {
+    Install,
+    InstallOnRequest,
+    BuildError,
+    CaskInstall,
+}
+
+impl AnalyticsCategory {
+    pub fn path(self) -> &'static str {
+        match self {
+            AnalyticsCategory::Install => "install",
+            AnalyticsCategory::InstallOnRequest => "install-on-request",
+            AnalyticsCategory::BuildError => "build-error",
+            AnalyticsCategory::CaskInstall => "cask-install",
+        }
+    }
+
+    /// Casks are only counted on install; requests and build errors are formula-only
+    fn for_package_type(package_type: PackageType) -> &'static [AnalyticsCategory] {
+        match package_type {
+            PackageType::Formula => &[
+                AnalyticsCategory::Install,
+                AnalyticsCategory::InstallOnRequest,
+                AnalyticsCategory::BuildError,
+            ],
+            PackageType::Cask => &[AnalyticsCategory::CaskInstall],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnalyticsWindow {
+    Days30,
+    Days90,
+    Days365,
+}
+
+impl AnalyticsWindow {
+    pub const ALL: [AnalyticsWindow; 3] = [AnalyticsWindow::Days30, AnalyticsWindow::Days90, AnalyticsWindow::Days365];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AnalyticsWindow::Days30 => "30d",
+            AnalyticsWindow::Days90 => "90d",
+            AnalyticsWindow::Days365 => "365d",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct PackageCounts {
+    installs: AnalyticsCounts,
+    installs_on_request: AnalyticsCounts,
+    build_errors: AnalyticsCounts,
+}
+
+/// Analytics for every package of one type, ranked by installs over 365 days
+pub struct AnalyticsSnapshot {
+    package_type: PackageType,
+    counts: HashMap<String, PackageCounts>,
+    ranks: HashMap<String, u32>,
+}
+
+impl AnalyticsSnapshot {
+    fn build(package_type: PackageType, counts: HashMap<String, PackageCounts>) -> Self {
+        let mut by_installs: Vec<(&String, u64)> = counts
+            .iter()
+            .filter(|(_, counts)| counts.installs.days_365 > 0)
+            .map(|(name, counts)| (name, counts.installs.days_365))
+            .collect();
+        by_installs.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+
+        // Equal counts share the rank of the first package with that count
+        let mut ranks = HashMap::with_capacity(by_installs.len());
+        let mut previous: Option<(u64, u32)> = None;
+        for (position, (name, installs)) in by_installs.into_iter().enumerate() {
+            let rank = match previous {
+                Some((previous_installs, previous_rank)) if previous_installs == installs => previous_rank,
+                _ => position as u32 + 1,
+            };
+            previous = Some((installs, rank));
+            ranks.insert(name.clone(), rank);
+        }
+
+        Self {
+            package_type,
+            counts,
+            ranks,
+        }
+    }
+
+    /// Install percentile of `name`, 0-100, without building its full analytics
+    pub fn popularity(&self, name: &str) -> f64 {
+        self.ranks
+            .get(name)
+            .map(|rank| percentile(*rank, self.ranks.len()))
+            .unwrap_or(0.0)
+    }
+
+    pub fn package_analytics(&self, name: &str) -> PackageAnalytics {
+        let Some(counts) = self.counts.get(name) else {
+            return PackageAnalytics::default();
+        };
+
+        let popularity_rank = self.ranks.get(name).copied();
+        let popularity = self.popularity(name);
+
+        to_package_analytics(self.package_type, counts.clone(), popularity, popularity_rank)
+    }
+}
+
+/// Keeps one analytics snapshot per package type, refreshed at most every few
+/// hours since the upstream data only changes daily.
+pub struct AnalyticsStore {
+    snapshots: RwLock<HashMap<PackageType, (Arc<AnalyticsSnapshot>, Instant)>>,
+}
+
+impl AnalyticsStore {
+    pub fn new() -> Self {
+        Self {
+            snapshots: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// The current snapshot, refreshing it when due. A failed refresh keeps
+    /// serving the previous snapshot; `None` means analytics were never
+    /// available.
+    pub async fn snapshot(&self, api_client: &ApiClient, package_type: PackageType) -> Option<Arc<AnalyticsSnapshot>> {
+        let current = self.snapshots.read().await.get(&package_type).cloned();
+
+        if let Some((snapshot, fetched_at)) = &current {
+            if fetched_at.elapsed() < ANALYTICS_REFRESH_INTERVAL {
+                return Some(Arc::clone(snapshot));
+            }
+        }
+
+        match fetch_snapshot(api_client, package_type).await {
+            Ok(snapshot) => {
+                let snapshot = Arc::new(snapshot);
+                self.snapshots.write().await.insert(package_type, (Arc::clone(&snapshot), Instant::now()));
+                Some(snapshot)
+            }
+            Err(e) => {
+                warn!("Failed to refresh {} analytics: {}", package_type, e);
+                current.map(|(snapshot, _)| snapshot)
+            }
+        }
+    }
+
+    /// The last snapshot without refreshing it, e.g. while offline
+    pub async fn cached(&self, package_type: PackageType) -> Option<Arc<AnalyticsSnapshot>> {
+        self.snapshots
+            .read()
+            .await
+            .get(&package_type)
+            .map(|(snapshot, _)| Arc::clone(snapshot))
+    }
+
+    pub async fn invalidate(&self) {
+        self.snapshots.write().await.clear();
+    }
+}
+
+impl Default for AnalyticsStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Builds analytics from the counts embedded in single package payloads,
+/// for when the analytics endpoints are unreachable. No rank is available.
+pub fn from_embedded(package_type: PackageType, analytics: &ApiAnalytics) -> PackageAnalytics {
+    let windowed = |counts: &HashMap<String, HashMap<String, u64>>| {
+        let mut result = AnalyticsCounts::default();
+        for window in AnalyticsWindow::ALL {
+            if let Some(by_name) = counts.get(window.as_str()) {
+                add_count(&mut result, window, by_name.values().sum());
+            }
+        }
+        result
+    };
+
+    let counts = PackageCounts {
+        installs: windowed(&analytics.install),
+        installs_on_request: windowed(&analytics.install_on_request),
+        build_errors: windowed(&analytics.build_error),
+    };
+
+    to_package_analytics(package_type, counts, 0.0, None)
+}
+
+async fn fetch_snapshot(api_client: &ApiClient, package_type: PackageType) -> Result<AnalyticsSnapshot, BrewDeckError> {
+    let start_time = Instant::now();
+    let mut counts: HashMap<String, PackageCounts> = HashMap::new();
+    let mut fetched = 0;
+    let mut last_error = None;
+
+    for &category in AnalyticsCategory::for_package_type(package_type) {
+        let (days_30, days_90, days_365) = tokio::join!(
+            api_client.fetch_analytics(category, AnalyticsWindow::Days30),
+            api_client.fetch_analytics(category, AnalyticsWindow::Days90),
+            api_client.fetch_analytics(category, AnalyticsWindow::Days365),
+        );
+
+        for (window, response) in AnalyticsWindow::ALL.into_iter().zip([days_30, days_90, days_365]) {
+            let response = match response {
+                Ok(response) => response,
+                Err(e) => {
+                    warn!("Skipping {} analytics for {}: {}", category.path(), window.as_str(), e);
+                    last_error = Some(e);
+                    continue;
+                }
+            };
+
+            fetched += 1;
+            for item in &response.items {
+                let Some(name) = item_name(item) else {
+                    continue;
+                };
+
+                let entry = counts.entry(name).or_default();
+                let target = match category {
+                    AnalyticsCategory::Install | AnalyticsCategory::CaskInstall => &mut entry.installs,
+                    AnalyticsCategory::InstallOnRequest => &mut entry.installs_on_request,
+                    AnalyticsCategory::BuildError => &mut entry.build_errors,
+                };
+                add_count(target, window, parse_count(&item.count));
+            }
+        }
+    }
+
+    if fetched == 0 {
+        return Err(last_error.unwrap_or_else(|| {
+            BrewDeckError::InternalError(format!("No analytics categories for {package_type}"))
+        }));
+    }
+
+    info!(
+        "Loaded {} analytics for {} packages from {} endpoints in {:?}",
+        package_type,
+        counts.len(),
+        fetched,
+        start_time.elapsed()
+    );
+
+    Ok(AnalyticsSnapshot::build(package_type, counts))
+}
+
+/// Formula items carry install options after the name (`wget --HEAD`); those
+/// are folded into the package's total.
+fn item_name(item: &ApiAnalyticsItem) -> Option<String> {
+    let name = item.formula.as_deref().or(item.cask.as_deref())?;
+    let name = name.split_whitespace().next()?;
+
+    Some(name.to_string())
+}
+
+fn parse_count(count: &serde_json::Value) -> u64 {
+    match count {
+        serde_json::Value::Number(number) => number.as_u64().unwrap_or(0),
+        serde_json::Value::String(text) => {
+            let digits: String = text.chars().filter(|c| *c != ',').collect();
+            digits.trim().parse().unwrap_or_else(|_| {
+                debug!("Unparseable analytics count: {}", text);
+                0
+            })
+        }
+        _ => 0,
+    }
+}
+
+fn add_count(counts: &mut AnalyticsCounts, window: AnalyticsWindow, count: u64) {
+    match window {
+        AnalyticsWindow::Days30 => counts.days_30 += count,
+        AnalyticsWindow::Days90 => counts.days_90 += count,
+        AnalyticsWindow::Days365 => counts.days_365 += count,
+    }
+}
+
+/// Share of ranked packages this one is at least as popular as, from 100 for
+/// the most installed down towards 0, rounded to one decimal.
+fn percentile(rank: u32, ranked_packages: usize) -> f64 {
+    if ranked_packages == 0 {
+        return 0.0;
+    }
+
+    let above = (rank - 1) as f64;
+    let value = (1.0 - above / ranked_packages as f64) * 100.0;
+    (value * 10.0).round() / 10.0
+}
+
+fn to_package_analytics(
+    package_type: PackageType,
+    counts: PackageCounts,
+    popularity: f64,
+    popularity_rank: Option<u32>,
+) -> PackageAnalytics {
+    let build_error_rate = match package_type {
+        PackageType::Formula if counts.installs.days_365 > 0 => {
+            Some(counts.build_errors.days_365 as f64 / counts.installs.days_365 as f64)
+        }
+        _ => None,
+    };
+
+    PackageAnalytics {
+        downloads_365d: counts.installs.days_365,
+        installs: counts.installs,
+        installs_on_request: counts.installs_on_request,
+        build_errors: counts.build_errors,
+        build_error_rate,
+        popularity,
+        popularity_rank,
+        rating: None,
+    }
+}
@@ -1,42 +1,101 @@
 use crate::error::{BrewDeckError, ErrorRecovery, retry_with_backoff};
+use crate::services::analytics::{AnalyticsCategory, AnalyticsWindow};
 use crate::services::brew_client::PackageType;
-use crate::services::package_service::{PackageAnalytics, PackageWarning, WarningType, WarningSeverity};
-use serde::{Deserialize, Serialize};
+use crate::services::cask_requirements;
+use crate::services::catalog_store::CatalogMetadata;
+use crate::services::http_client::HttpClientFactory;
+use crate::services::package_service::{BottleAvailability, PackageAnalytics, PackageWarning, WarningType, WarningSeverity};
+use crate::services::platform::HostPlatform;
+use crate::services::rate_limiter::{parse_retry_after, RateLimiter};
+use crate::services::signature::JwsVerifier;
+use serde::de::{DeserializeSeed, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::collections::HashMap;
+use std::io::Read;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::RwLock;
 use tracing::{debug, info, warn};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ApiPackageInfo {
     pub name: String,
     pub token: Option<String>, // For casks
+    pub full_name: String, // Tap-qualified name or token
+    pub aliases: Vec<String>,
+    pub old_names: Vec<String>,
     pub version: String,
+    pub revision: u32,
     pub description: String,
     pub homepage: Option<String>,
     pub dependencies: Vec<String>,
+    pub build_dependencies: Vec<String>,
+    /// Dependencies macOS provides; brew installs them on Linux
+    pub uses_from_macos: Vec<String>,
     pub conflicts: Vec<String>,
     pub caveats: Option<String>,
     pub analytics: ApiAnalytics,
+    /// Bottle for the host platform; `None` for casks
+    pub bottle: Option<BottleAvailability>,
+    /// macOS version or architecture constraints the host does not meet
+    pub unmet_requirements: Vec<String>,
     pub deprecated: bool,
     pub disabled: bool,
+    pub auto_updates: Option<bool>,
     pub warnings: Vec<String>,
     pub license: Option<String>,
     pub tap: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Install counts per window (`30d`, `90d`, `365d`), keyed by package name
+/// and install options, e.g. `wget` and `wget --HEAD`. Only the single
+/// package endpoints include analytics.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ApiAnalytics {
-    pub install: HashMap<String, u64>,
-    pub install_on_request: HashMap<String, u64>,
-    pub build_error: HashMap<String, u64>,
+    pub install: HashMap<String, HashMap<String, u64>>,
+    pub install_on_request: HashMap<String, HashMap<String, u64>>,
+    pub build_error: HashMap<String, HashMap<String, u64>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// One page of `analytics/<category>/<window>.json`, ranked by count
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiAnalyticsResponse {
+    pub category: String,
+    pub total_items: u64,
+    pub start_date: String,
+    pub end_date: String,
+    pub total_count: u64,
+    pub items: Vec<ApiAnalyticsItem>,
+}
+
+/// `count` is a comma-grouped string such as `"1,234"`. Formula categories
+/// name the package under `formula` (with install options appended, e.g.
+/// `wget --HEAD`), cask categories under `cask`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiAnalyticsItem {
+    pub formula: Option<String>,
+    pub cask: Option<String>,
+    pub count: serde_json::Value,
+}
+
+// The formulae.brew.sh payloads grow new fields and leave old ones out
+// depending on the endpoint, so every response struct takes defaults and
+// ignores unknown fields.
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ApiFormulaResponse {
     pub name: String,
     pub full_name: String,
     pub tap: String,
     pub oldname: Option<String>,
+    pub oldnames: Vec<String>,
     pub aliases: Vec<String>,
     pub versioned_formulae: Vec<String>,
     pub desc: Option<String>,
@@ -46,19 +105,20 @@ pub struct ApiFormulaResponse {
     pub urls: ApiUrls,
     pub revision: u32,
     pub version_scheme: u32,
-    pub bottle: ApiBottle,
+    pub bottle: ApiBottleSpecs,
     pub keg_only: bool,
-    pub keg_only_reason: Option<String>,
-    pub options: Vec<String>,
+    pub keg_only_reason: Option<ApiKegOnlyReason>,
+    pub options: Vec<serde_json::Value>,
     pub build_dependencies: Vec<String>,
     pub dependencies: Vec<String>,
     pub test_dependencies: Vec<String>,
     pub recommended_dependencies: Vec<String>,
     pub optional_dependencies: Vec<String>,
-    pub uses_from_macos: Vec<String>,
-    pub requirements: Vec<String>,
+    /// Plain names or `{"name": "build"}`-style objects
+    pub uses_from_macos: Vec<serde_json::Value>,
+    pub requirements: Vec<serde_json::Value>,
     pub conflicts_with: Vec<String>,
-    pub conflicts_with_reasons: Vec<String>,
+    pub conflicts_with_reasons: Vec<Option<String>>,
     pub link_overwrite: Vec<String>,
     pub caveats: Option<String>,
     pub installed: Vec<ApiInstalled>,
@@ -74,10 +134,12 @@ pub struct ApiFormulaResponse {
     pub analytics: ApiAnalytics,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ApiCaskResponse {
     pub token: String,
     pub full_token: String,
+    pub old_tokens: Vec<String>,
     pub tap: String,
     pub name: Vec<String>,
     pub desc: Option<String>,
@@ -92,132 +154,423 @@ pub struct ApiCaskResponse {
     pub caveats: Option<String>,
     pub depends_on: ApiDependsOn,
     pub conflicts_with: Option<serde_json::Value>,
-    pub container: Option<String>,
+    pub container: Option<serde_json::Value>,
     pub auto_updates: Option<bool>,
-    pub tap_git_head: String,
+    pub deprecated: bool,
+    pub deprecation_date: Option<String>,
+    pub deprecation_reason: Option<String>,
+    pub disabled: bool,
+    pub disable_date: Option<String>,
+    pub disable_reason: Option<String>,
+    pub tap_git_head: Option<String>,
     pub languages: Vec<String>,
-    pub ruby_source_path: String,
+    pub ruby_source_path: Option<String>,
     pub ruby_source_checksum: HashMap<String, String>,
     pub variations: HashMap<String, serde_json::Value>,
     pub analytics: ApiAnalytics,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ApiVersions {
-    pub stable: String,
+    pub stable: Option<String>,
     pub head: Option<String>,
     pub bottle: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ApiUrls {
-    pub stable: ApiUrl,
+    pub stable: Option<ApiUrl>,
     pub head: Option<ApiUrl>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ApiUrl {
     pub url: String,
     pub tag: Option<String>,
     pub revision: Option<String>,
+    pub branch: Option<String>,
+    pub using: Option<String>,
     pub checksum: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiKegOnlyReason {
+    pub reason: String,
+    pub explanation: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiBottleSpecs {
+    pub stable: Option<ApiBottle>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ApiBottle {
     pub rebuild: u32,
     pub root_url: String,
     pub files: HashMap<String, ApiBottleFile>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ApiBottleFile {
     pub cellar: String,
     pub url: String,
     pub sha256: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ApiInstalled {
     pub version: String,
     pub used_options: Vec<String>,
     pub built_as_bottle: bool,
     pub poured_from_bottle: bool,
-    pub time: u64,
-    pub runtime_dependencies: Vec<String>,
+    pub time: Option<u64>,
+    pub runtime_dependencies: Vec<serde_json::Value>,
     pub installed_as_dependency: bool,
     pub installed_on_request: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ApiDependsOn {
+    /// Comparison to versions or names, e.g. `{">=": ["12"]}`
     pub macos: Option<HashMap<String, Vec<String>>>,
-    pub arch: Option<HashMap<String, Vec<String>>>,
+    pub arch: Option<Vec<ApiArchRequirement>>,
     pub formula: Vec<String>,
     pub cask: Vec<String>,
 }
 
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiArchRequirement {
+    #[serde(rename = "type")]
+    pub arch_type: String,
+    pub bits: Option<u32>,
+}
+
+pub const DEFAULT_API_DOMAIN: &str = "https://formulae.brew.sh/api";
+
+/// Where catalog requests go. Mirrors are tried in order when the base URL fails.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiEndpointConfig {
+    pub base_url: String,
+    pub mirrors: Vec<String>,
+}
+
+impl Default for ApiEndpointConfig {
+    fn default() -> Self {
+        Self::from_env()
+    }
+}
+
+impl ApiEndpointConfig {
+    /// Follows brew itself by honoring `HOMEBREW_API_DOMAIN`
+    pub fn from_env() -> Self {
+        let base_url = std::env::var("HOMEBREW_API_DOMAIN")
+            .ok()
+            .filter(|domain| !domain.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_API_DOMAIN.to_string());
+        
+        Self {
+            base_url,
+            mirrors: Vec::new(),
+        }
+    }
+    
+    /// Where the endpoints are kept unless configured otherwise
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("BrewDeck").join("api-endpoints.json"))
+    }
+    
+    /// Reads the endpoints file; a missing file means `from_env`
+    pub fn load(path: &Path) -> Result<Self, BrewDeckError> {
+        let endpoints: Self = match std::fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| {
+                BrewDeckError::InvalidConfiguration(format!("Invalid API endpoints {}: {}", path.display(), e))
+            })?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::from_env()),
+            Err(e) => return Err(e.into()),
+        };
+        
+        endpoints.validate()?;
+        Ok(endpoints)
+    }
+    
+    pub fn save(&self, path: &Path) -> Result<(), BrewDeckError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        
+        let temp_path = path.with_extension("json.tmp");
+        std::fs::write(&temp_path, serde_json::to_vec_pretty(self)?)?;
+        std::fs::rename(&temp_path, path)?;
+        
+        info!("Saved API endpoints to {}", path.display());
+        Ok(())
+    }
+    
+    pub fn validate(&self) -> Result<(), BrewDeckError> {
+        for url in std::iter::once(&self.base_url).chain(&self.mirrors) {
+            if !url.starts_with("https://") && !url.starts_with("http://") {
+                return Err(BrewDeckError::InvalidConfiguration(
+                    format!("API endpoint must be an http(s) URL: {}", url)
+                ));
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Base URL followed by mirrors, without trailing slashes or duplicates
+    pub fn endpoints(&self) -> Vec<String> {
+        let mut endpoints: Vec<String> = Vec::new();
+        
+        for url in std::iter::once(&self.base_url).chain(&self.mirrors) {
+            let url = url.trim().trim_end_matches('/').to_string();
+            if !url.is_empty() && !endpoints.contains(&url) {
+                endpoints.push(url);
+            }
+        }
+        
+        endpoints
+    }
+}
+
+/// Outcome of a conditional catalog request
+pub enum CatalogResponse {
+    /// The server confirmed the stored copy with a 304
+    NotModified,
+    /// The body was written to the download path
+    Modified {
+        source_url: String,
+        size_bytes: u64,
+        etag: Option<String>,
+        last_modified: Option<String>,
+        /// Key id the payload's signature was verified with
+        verified_by: Option<String>,
+    },
+}
+
 pub struct ApiClient {
-    client: reqwest::Client,
-    base_url: String,
+    http: Arc<HttpClientFactory>,
+    rate_limiter: Arc<RateLimiter>,
+    endpoints: RwLock<ApiEndpointConfig>,
+    verifier: RwLock<Option<Arc<JwsVerifier>>>,
 }
 
 impl ApiClient {
-    pub fn new() -> Result<Self, BrewDeckError> {
-        let client = reqwest::Client::builder()
-            .timeout(Duration::from_secs(30))
-            .user_agent("BrewDeck/1.0")
-            .build()
-            .map_err(|e| BrewDeckError::NetworkError(e.to_string()))?;
+    pub fn with_endpoints(
+        http: Arc<HttpClientFactory>,
+        rate_limiter: Arc<RateLimiter>,
+        endpoints: ApiEndpointConfig,
+    ) -> Result<Self, BrewDeckError> {
+        endpoints.validate()?;
         
         Ok(Self {
-            client,
-            base_url: "https://formulae.brew.sh/api".to_string(),
+            http,
+            rate_limiter,
+            endpoints: RwLock::new(endpoints),
+            verifier: RwLock::new(None),
         })
     }
     
-    pub async fn fetch_all_formulae(&self) -> Result<Vec<ApiFormulaResponse>, BrewDeckError> {
-        let url = format!("{}/formula.json", self.base_url);
-        self.fetch_with_retry(&url).await
+    pub async fn endpoints(&self) -> ApiEndpointConfig {
+        self.endpoints.read().await.clone()
     }
     
-    pub async fn fetch_all_casks(&self) -> Result<Vec<ApiCaskResponse>, BrewDeckError> {
-        let url = format!("{}/cask.json", self.base_url);
-        self.fetch_with_retry(&url).await
+    pub async fn set_endpoints(&self, endpoints: ApiEndpointConfig) -> Result<(), BrewDeckError> {
+        endpoints.validate()?;
+        info!("API endpoints updated: {:?}", endpoints.endpoints());
+        *self.endpoints.write().await = endpoints;
+        Ok(())
     }
     
-    pub async fn fetch_formula(&self, name: &str) -> Result<ApiFormulaResponse, BrewDeckError> {
-        let url = format!("{}/formula/{}.json", self.base_url, name);
-        self.fetch_with_retry(&url).await
+    /// With a verifier set, catalogs are fetched as signed JWS envelopes and
+    /// only their verified payload is returned
+    pub async fn set_verifier(&self, verifier: Option<JwsVerifier>) {
+        *self.verifier.write().await = verifier.map(Arc::new);
     }
     
-    pub async fn fetch_cask(&self, name: &str) -> Result<ApiCaskResponse, BrewDeckError> {
-        let url = format!("{}/cask/{}.json", self.base_url, name);
-        self.fetch_with_retry(&url).await
+    /// Downloads `formula.json` or `cask.json` to `download_path`, sending the
+    /// validators of `stored` when it came from the same URL so an unchanged
+    /// catalog costs a 304 instead of several megabytes.
+    pub async fn fetch_catalog(
+        &self,
+        package_type: PackageType,
+        stored: Option<&CatalogMetadata>,
+        download_path: &Path,
+    ) -> Result<CatalogResponse, BrewDeckError> {
+        let verifier = self.verifier.read().await.clone();
+        let path = match verifier {
+            Some(_) => format!("{package_type}.jws.json"),
+            None => format!("{package_type}.json"),
+        };
+        
+        let response = self.with_failover(&path, |url| async move {
+            let validators = stored.filter(|stored| stored.source_url == url);
+            self.fetch_conditional_once(&url, validators, download_path).await
+        }).await?;
+        
+        // Verified outside the retry loop: a bad signature will not fix itself.
+        // The envelope holds the payload as one JSON string, so unlike a plain
+        // catalog it is checked in memory before the payload replaces it.
+        match (response, verifier) {
+            (CatalogResponse::Modified { source_url, etag, last_modified, .. }, Some(verifier)) => {
+                let payload = verifier.verify(&tokio::fs::read(download_path).await?)?;
+                tokio::fs::write(download_path, &payload).await?;
+                Ok(CatalogResponse::Modified {
+                    source_url,
+                    size_bytes: payload.len() as u64,
+                    etag,
+                    last_modified,
+                    verified_by: Some(verifier.key_id().to_string()),
+                })
+            }
+            (response, _) => Ok(response),
+        }
     }
     
-    pub async fn fetch_analytics(&self, package_type: PackageType) -> Result<HashMap<String, u64>, BrewDeckError> {
-        let endpoint = match package_type {
-            PackageType::Formula => "analytics/install/365d.json",
-            PackageType::Cask => "analytics-linux/cask-install/365d.json",
-        };
+    /// Cheap reachability check: any HTTP answer from an endpoint counts
+    pub async fn probe(&self) -> Result<(), BrewDeckError> {
+        let endpoints = self.endpoints.read().await.endpoints();
+        let client = self.http.client().await;
+        let mut last_error = None;
         
-        let url = format!("{}/{}", self.base_url, endpoint);
-        let response: HashMap<String, HashMap<String, u64>> = self.fetch_with_retry(&url).await?;
+        for base_url in endpoints {
+            let request = client
+                .head(format!("{}/formula.json", base_url))
+                .timeout(Duration::from_secs(5));
+            
+            match request.send().await {
+                Ok(_) => return Ok(()),
+                Err(e) => last_error = Some(BrewDeckError::from(e)),
+            }
+        }
         
-        // Extract the items from the response
-        Ok(response.get("items").cloned().unwrap_or_default())
+        Err(last_error.unwrap_or_else(|| {
+            BrewDeckError::InvalidConfiguration("No API endpoints configured".to_string())
+        }))
+    }
+    
+    pub async fn fetch_analytics(
+        &self,
+        category: AnalyticsCategory,
+        window: AnalyticsWindow,
+    ) -> Result<ApiAnalyticsResponse, BrewDeckError> {
+        let root = HostPlatform::current().analytics_root();
+        self.fetch_with_retry(&format!("{}/{}/{}.json", root, category.path(), window.as_str())).await
     }
     
-    async fn fetch_with_retry<T>(&self, url: &str) -> Result<T, BrewDeckError>
+    /// Fetches `path` relative to the configured endpoints, failing over to the
+    /// next mirror once retries against the current one are exhausted.
+    async fn fetch_with_retry<T>(&self, path: &str) -> Result<T, BrewDeckError>
     where
         T: for<'de> serde::Deserialize<'de>,
     {
-        let recovery = ErrorRecovery::new()
-            .with_max_retries(3)
-            .with_backoff(1000);
+        self.with_failover(path, |url| async move { self.fetch_once(&url).await }).await
+    }
+    
+    /// Runs `request` against each endpoint in order with `path` appended,
+    /// retrying each before moving on to the next.
+    async fn with_failover<T, F, Fut>(&self, path: &str, mut request: F) -> Result<T, BrewDeckError>
+    where
+        F: FnMut(String) -> Fut,
+        Fut: std::future::Future<Output = Result<T, BrewDeckError>>,
+    {
+        let endpoints = self.endpoints.read().await.endpoints();
+        let mut last_error = None;
+        
+        for (index, base_url) in endpoints.iter().enumerate() {
+            let url = format!("{}/{}", base_url, path);
+            
+            // Fail over quickly while there are mirrors left to try
+            let max_retries = if index + 1 < endpoints.len() { 1 } else { 3 };
+            let recovery = ErrorRecovery::new()
+                .with_max_retries(max_retries)
+                .with_backoff(1000);
+            
+            match retry_with_backoff(|| request(url.clone()), recovery).await {
+                Ok(data) => {
+                    if index > 0 {
+                        info!("Fetched {} from mirror {}", path, base_url);
+                    }
+                    return Ok(data);
+                }
+                Err(e) => {
+                    warn!("API endpoint {} failed for {}: {}", base_url, path, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+        
+        Err(last_error.unwrap_or_else(|| {
+            BrewDeckError::InvalidConfiguration("No API endpoints configured".to_string())
+        }))
+    }
+    
+    /// Streams a changed catalog to `download_path` chunk by chunk instead of
+    /// buffering it
+    async fn fetch_conditional_once(
+        &self,
+        url: &str,
+        stored: Option<&CatalogMetadata>,
+        download_path: &Path,
+    ) -> Result<CatalogResponse, BrewDeckError> {
+        debug!("Fetching from API: {} (conditional: {})", url, stored.is_some());
+        
+        let mut request = self.http.client().await.get(url);
+        if let Some(stored) = stored {
+            if let Some(etag) = &stored.etag {
+                request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &stored.last_modified {
+                request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+        
+        self.rate_limiter.acquire(url).await;
+        let response = request.send().await?;
         
-        retry_with_backoff(|| self.fetch_once(url), recovery).await
+        if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+            return Ok(CatalogResponse::NotModified);
+        }
+        
+        let mut response = self.check_status(response).await?;
+        
+        let header = |name: reqwest::header::HeaderName| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_string())
+        };
+        let etag = header(reqwest::header::ETAG);
+        let last_modified = header(reqwest::header::LAST_MODIFIED);
+        
+        let mut file = tokio::fs::File::create(download_path).await?;
+        let mut size_bytes = 0;
+        while let Some(chunk) = response.chunk().await? {
+            file.write_all(&chunk).await?;
+            size_bytes += chunk.len() as u64;
+        }
+        file.flush().await?;
+        
+        Ok(CatalogResponse::Modified {
+            source_url: url.to_string(),
+            size_bytes,
+            etag,
+            last_modified,
+            verified_by: None,
+        })
     }
     
     async fn fetch_once<T>(&self, url: &str) -> Result<T, BrewDeckError>
@@ -226,58 +579,200 @@ impl ApiClient {
     {
         debug!("Fetching from API: {}", url);
         
-        let response = self.client.get(url).send().await?;
+        let client = self.http.client().await;
+        self.rate_limiter.acquire(url).await;
+        let response = self.check_status(client.get(url).send().await?).await?;
         
-        if !response.status().is_success() {
-            return Err(BrewDeckError::NetworkError(
-                format!("API request failed with status: {}", response.status())
+        let data = response.json().await?;
+        Ok(data)
+    }
+    
+    /// Turns error statuses into errors, pausing requests to the host when
+    /// it rate limits us and says for how long
+    async fn check_status(&self, response: reqwest::Response) -> Result<reqwest::Response, BrewDeckError> {
+        let status = response.status();
+        
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            let retry_after = response
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(parse_retry_after);
+            
+            if let Some(retry_after) = retry_after {
+                self.rate_limiter.pause(response.url().as_str(), retry_after).await;
+            }
+            
+            return Err(BrewDeckError::RateLimitExceeded(
+                format!("{} answered {}", response.url(), status),
+                // Round up so a sub-second date-based delay is still waited out
+                retry_after.map(|delay| delay.as_secs() + u64::from(delay.subsec_nanos() > 0)),
             ));
         }
         
-        let data = response.json().await?;
-        Ok(data)
+        if !status.is_success() {
+            return Err(BrewDeckError::HttpStatusError(
+                format!("{} answered {}", response.url(), status),
+                status.as_u16(),
+            ));
+        }
+        
+        Ok(response)
+    }
+}
+
+/// Deserializes a JSON array of `T`, mapping each element through `convert`
+/// as soon as it has been read.
+struct CatalogSeed<T, F> {
+    convert: F,
+    element: PhantomData<T>,
+}
+
+impl<T, F> CatalogSeed<T, F>
+where
+    F: FnMut(T) -> ApiPackageInfo,
+{
+    fn new(convert: F) -> Self {
+        Self {
+            convert,
+            element: PhantomData,
+        }
+    }
+}
+
+impl<'de, T, F> DeserializeSeed<'de> for CatalogSeed<T, F>
+where
+    T: Deserialize<'de>,
+    F: FnMut(T) -> ApiPackageInfo,
+{
+    type Value = Vec<ApiPackageInfo>;
+    
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, T, F> Visitor<'de> for CatalogSeed<T, F>
+where
+    T: Deserialize<'de>,
+    F: FnMut(T) -> ApiPackageInfo,
+{
+    type Value = Vec<ApiPackageInfo>;
+    
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("an array of catalog entries")
+    }
+    
+    fn visit_seq<A>(mut self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut infos = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        
+        while let Some(element) = seq.next_element::<T>()? {
+            infos.push((self.convert)(element));
+        }
+        
+        infos.shrink_to_fit();
+        Ok(infos)
     }
 }
 
 pub struct PackageParser;
 
 impl PackageParser {
-    pub fn parse_formula_to_package_info(formula: ApiFormulaResponse) -> ApiPackageInfo {
+    /// Parses a `formula.json` / `cask.json` body as it is read, converting
+    /// each entry to an `ApiPackageInfo` before reading the next, so neither
+    /// the whole body nor the full response structs are ever held at once.
+    /// Bottles, variations and requirements are resolved for `host`.
+    pub fn parse_catalog<R: Read>(
+        package_type: PackageType,
+        body: R,
+        host: &HostPlatform,
+    ) -> Result<Vec<ApiPackageInfo>, BrewDeckError> {
+        let mut deserializer = serde_json::Deserializer::from_reader(body);
+        
+        let infos = match package_type {
+            PackageType::Formula => CatalogSeed::new(|formula| Self::parse_formula_to_package_info(formula, host))
+                .deserialize(&mut deserializer)?,
+            PackageType::Cask => CatalogSeed::new(|cask| Self::parse_cask_to_package_info(cask, host))
+                .deserialize(&mut deserializer)?,
+        };
+        deserializer.end()?;
+        
+        Ok(infos)
+    }
+    
+    pub fn parse_formula_to_package_info(formula: ApiFormulaResponse, host: &HostPlatform) -> ApiPackageInfo {
         let warnings = Self::extract_formula_warnings(&formula);
+        let bottle = Self::host_bottle(&formula.bottle, host);
+        
+        // Older payloads only carry the single `oldname`
+        let mut old_names = formula.oldnames;
+        if let Some(oldname) = formula.oldname {
+            if !old_names.contains(&oldname) {
+                old_names.push(oldname);
+            }
+        }
         
         ApiPackageInfo {
             name: formula.name,
             token: None,
-            version: formula.versions.stable,
+            full_name: formula.full_name,
+            aliases: formula.aliases,
+            old_names,
+            version: formula.versions.stable.unwrap_or_else(|| "unknown".to_string()),
+            revision: formula.revision,
             description: formula.desc.unwrap_or_else(|| "No description available".to_string()),
             homepage: formula.homepage,
             dependencies: formula.dependencies,
+            build_dependencies: formula.build_dependencies,
+            uses_from_macos: Self::uses_from_macos_names(&formula.uses_from_macos),
             conflicts: formula.conflicts_with,
             caveats: formula.caveats,
             analytics: formula.analytics,
+            bottle: Some(bottle),
+            unmet_requirements: Vec::new(),
             deprecated: formula.deprecated,
             disabled: formula.disabled,
+            auto_updates: None,
             warnings,
             license: formula.license,
             tap: formula.tap,
         }
     }
     
-    pub fn parse_cask_to_package_info(cask: ApiCaskResponse) -> ApiPackageInfo {
+    /// Resolves the cask's variation for the host before reading it, so
+    /// version, URL and requirements are the ones brew would install
+    pub fn parse_cask_to_package_info(mut cask: ApiCaskResponse, host: &HostPlatform) -> ApiPackageInfo {
+        cask_requirements::apply_variation(&mut cask, host);
+        let unmet_requirements = cask_requirements::unmet_requirements(&cask.depends_on, host);
         let warnings = Self::extract_cask_warnings(&cask);
         
         ApiPackageInfo {
             name: cask.name.first().cloned().unwrap_or(cask.token.clone()),
             token: Some(cask.token),
+            full_name: cask.full_token,
+            aliases: Vec::new(),
+            old_names: cask.old_tokens,
             version: cask.version,
+            revision: 0,
             description: cask.desc.unwrap_or_else(|| "No description available".to_string()),
             homepage: cask.homepage,
             dependencies: cask.depends_on.formula,
+            build_dependencies: Vec::new(),
+            uses_from_macos: Vec::new(),
             conflicts: Vec::new(), // Casks don't typically have conflicts in the same way
             caveats: cask.caveats,
             analytics: cask.analytics,
-            deprecated: false, // Casks don't have deprecated field in the same way
-            disabled: false,
+            bottle: None,
+            unmet_requirements,
+            deprecated: cask.deprecated,
+            disabled: cask.disabled,
+            auto_updates: cask.auto_updates,
             warnings,
             license: None, // Casks don't typically have license info
             tap: cask.tap,
@@ -286,6 +781,46 @@ impl PackageParser {
     
 
     
+    /// Entries are plain names or `{"name": "build"}` with the dependency type
+    fn uses_from_macos_names(entries: &[serde_json::Value]) -> Vec<String> {
+        entries
+            .iter()
+            .filter_map(|entry| match entry {
+                serde_json::Value::String(name) => Some(name.clone()),
+                serde_json::Value::Object(map) => map.keys().next().cloned(),
+                _ => None,
+            })
+            .collect()
+    }
+    
+    /// Picks the bottle brew would pour on `host`, keeping only that one
+    /// file so catalog records stay small
+    fn host_bottle(specs: &ApiBottleSpecs, host: &HostPlatform) -> BottleAvailability {
+        let host_tag = host.bottle_tag().unwrap_or_else(|| "unsupported".to_string());
+        let file = specs.stable.as_ref().and_then(|bottle| {
+            host.compatible_bottle_tags()
+                .into_iter()
+                .find_map(|tag| bottle.files.get(&tag).map(|file| (tag, file)))
+        });
+        
+        match file {
+            Some((tag, file)) => BottleAvailability {
+                host_tag,
+                available: true,
+                tag: Some(tag),
+                url: Some(file.url.clone()),
+                sha256: Some(file.sha256.clone()),
+            },
+            None => BottleAvailability {
+                host_tag,
+                available: false,
+                tag: None,
+                url: None,
+                sha256: None,
+            },
+        }
+    }
+    
     fn extract_formula_warnings(formula: &ApiFormulaResponse) -> Vec<String> {
         let mut warnings = Vec::new();
         
@@ -308,7 +843,15 @@ impl PackageParser {
         if formula.keg_only {
             let mut warning = "This formula is keg-only".to_string();
             if let Some(reason) = &formula.keg_only_reason {
-                warning.push_str(&format!(": {}", reason));
+                // Reasons are symbols such as `:provided_by_macos` unless explained
+                let reason = if reason.explanation.is_empty() {
+                    reason.reason.trim_start_matches(':').replace('_', " ")
+                } else {
+                    reason.explanation.clone()
+                };
+                if !reason.is_empty() {
+                    warning.push_str(&format!(": {}", reason));
+                }
             }
             warnings.push(warning);
         }
@@ -326,6 +869,22 @@ impl PackageParser {
     fn extract_cask_warnings(cask: &ApiCaskResponse) -> Vec<String> {
         let mut warnings = Vec::new();
         
+        if cask.deprecated {
+            let mut warning = "This cask is deprecated".to_string();
+            if let Some(reason) = &cask.deprecation_reason {
+                warning.push_str(&format!(": {}", reason));
+            }
+            warnings.push(warning);
+        }
+        
+        if cask.disabled {
+            let mut warning = "This cask is disabled".to_string();
+            if let Some(reason) = &cask.disable_reason {
+                warning.push_str(&format!(": {}", reason));
+            }
+            warnings.push(warning);
+        }
+        
         if cask.auto_updates == Some(false) {
             warnings.push("This application does not auto-update".to_string());
         }
@@ -380,4 +939,51 @@ impl PackageParser {
             (WarningType::Compatibility, WarningSeverity::Low)
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const FORMULA_FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/formula.json");
+    
+    #[test]
+    fn parses_catalog_from_reader() {
+        let file = std::fs::File::open(FORMULA_FIXTURE).unwrap();
+        let infos = PackageParser::parse_catalog(PackageType::Formula, std::io::BufReader::new(file), HostPlatform::current()).unwrap();
+        
+        let names: Vec<_> = infos.iter().map(|info| info.name.as_str()).collect();
+        assert_eq!(names, ["wget", "git-lfs", "youtube-dl"]);
+        assert_eq!(infos[0].version, "1.24.5");
+        assert_eq!(infos[0].dependencies, ["libidn2", "openssl@3"]);
+        assert_eq!(infos[1].license.as_deref(), Some("MIT"));
+        assert_eq!(infos[2].aliases, ["yt-dl"]);
+        assert_eq!(infos[2].revision, 3);
+        assert!(infos[2].deprecated);
+    }
+    
+    #[test]
+    fn rejects_trailing_garbage() {
+        let result = PackageParser::parse_catalog(PackageType::Formula, &br#"[{"name": "wget"}] x"#[..], HostPlatform::current());
+        assert!(result.is_err());
+    }
+    
+    #[test]
+    fn saves_and_loads_api_endpoints() {
+        let path = std::env::temp_dir().join(format!("brewdeck-api-endpoints-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(ApiEndpointConfig::load(&path).unwrap().base_url, ApiEndpointConfig::from_env().base_url);
+        
+        let endpoints = ApiEndpointConfig {
+            base_url: "https://mirror.example.com/api".to_string(),
+            mirrors: vec!["https://formulae.brew.sh/api".to_string()],
+        };
+        endpoints.save(&path).unwrap();
+        let loaded = ApiEndpointConfig::load(&path).unwrap();
+        assert_eq!(loaded.endpoints(), endpoints.endpoints());
+        
+        std::fs::write(&path, r#"{"base_url": "ftp://mirror.example.com"}"#).unwrap();
+        assert!(matches!(ApiEndpointConfig::load(&path), Err(BrewDeckError::InvalidConfiguration(_))));
+        
+        std::fs::remove_file(&path).unwrap();
+    }
+}
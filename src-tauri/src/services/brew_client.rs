@@ -1,8 +1,14 @@
 use crate::error::{BrewDeckError, ErrorRecovery, retry_with_backoff};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::process::Command as AsyncCommand;
+use tokio::sync::RwLock;
 use tracing::{debug, error, info, warn};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,16 +41,337 @@ pub struct CommandResult {
     pub success: bool,
 }
 
+/// Homebrew installation details collected from `brew config` and `brew --version`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BrewEnvironment {
+    pub homebrew_version: String,
+    pub origin: Option<String>,
+    pub head: Option<String>,
+    pub last_commit: Option<String>,
+    pub core_tap_head: Option<String>,
+    pub core_tap_last_commit: Option<String>,
+    pub core_tap_json: Option<String>,
+    pub prefix: Option<String>,
+    pub repository: Option<String>,
+    pub cellar: Option<String>,
+    pub ruby: Option<String>,
+    pub cpu: Option<String>,
+    pub os: Option<String>,
+    pub kernel: Option<String>,
+    pub clt: Option<String>,
+    pub xcode: Option<String>,
+    pub rosetta: Option<bool>,
+    pub env_overrides: HashMap<String, String>,
+    pub raw: HashMap<String, String>,
+    pub collected_at: DateTime<Utc>,
+}
+
+impl BrewEnvironment {
+    pub fn parse(config_output: &str, version_output: &str) -> Self {
+        let mut raw = HashMap::new();
+        
+        for line in config_output.lines() {
+            if let Some((key, value)) = line.split_once(": ") {
+                raw.insert(key.trim().to_string(), value.trim().to_string());
+            }
+        }
+        
+        // `brew config` prints "N/A" for tools that are not installed
+        let field = |key: &str| {
+            raw.get(key)
+                .filter(|value| !value.is_empty() && value.as_str() != "N/A")
+                .cloned()
+        };
+        
+        let homebrew_version = version_output
+            .lines()
+            .next()
+            .and_then(|line| line.strip_prefix("Homebrew "))
+            .map(|version| version.trim().to_string())
+            .or_else(|| field("HOMEBREW_VERSION"))
+            .unwrap_or_else(|| "unknown".to_string());
+        
+        // Settings that brew reports about itself rather than user overrides
+        let reported_settings = [
+            "HOMEBREW_VERSION",
+            "HOMEBREW_PREFIX",
+            "HOMEBREW_REPOSITORY",
+            "HOMEBREW_CELLAR",
+        ];
+        let env_overrides = raw
+            .iter()
+            .filter(|(key, _)| key.starts_with("HOMEBREW_") && !reported_settings.contains(&key.as_str()))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        
+        Self {
+            homebrew_version,
+            origin: field("ORIGIN"),
+            head: field("HEAD"),
+            last_commit: field("Last commit"),
+            core_tap_head: field("Core tap HEAD"),
+            core_tap_last_commit: field("Core tap last commit"),
+            core_tap_json: field("Core tap JSON"),
+            prefix: field("HOMEBREW_PREFIX"),
+            repository: field("HOMEBREW_REPOSITORY"),
+            cellar: field("HOMEBREW_CELLAR"),
+            ruby: field("Homebrew Ruby"),
+            cpu: field("CPU"),
+            os: field("macOS").or_else(|| field("OS")),
+            kernel: field("Kernel"),
+            clt: field("CLT"),
+            xcode: field("Xcode"),
+            rosetta: field("Rosetta 2").map(|value| value == "true"),
+            env_overrides,
+            raw,
+            collected_at: Utc::now(),
+        }
+    }
+    
+    /// One-line description used when attaching the environment to error reports
+    pub fn summary(&self) -> String {
+        let mut parts = vec![format!("Homebrew {}", self.homebrew_version)];
+        
+        if let Some(prefix) = &self.prefix {
+            parts.push(format!("prefix {prefix}"));
+        }
+        if let Some(os) = &self.os {
+            parts.push(format!("OS {os}"));
+        }
+        if let Some(cpu) = &self.cpu {
+            parts.push(format!("CPU {cpu}"));
+        }
+        if let Some(clt) = &self.clt {
+            parts.push(format!("CLT {clt}"));
+        }
+        if let Some(xcode) = &self.xcode {
+            parts.push(format!("Xcode {xcode}"));
+        }
+        if !self.env_overrides.is_empty() {
+            let mut overrides: Vec<_> = self.env_overrides.keys().cloned().collect();
+            overrides.sort();
+            parts.push(format!("overrides {}", overrides.join(",")));
+        }
+        
+        parts.join(", ")
+    }
+}
+
+/// What changed in the taps during a `brew update`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateSummary {
+    pub already_up_to_date: bool,
+    pub updated_taps: Vec<String>,
+    pub new_formulae: Vec<String>,
+    pub new_casks: Vec<String>,
+    pub updated_formulae: Vec<String>,
+    pub updated_casks: Vec<String>,
+    pub deleted_formulae: Vec<String>,
+    pub deleted_casks: Vec<String>,
+    pub renamed_formulae: HashMap<String, String>,
+    pub outdated_formulae: Vec<String>,
+    pub outdated_casks: Vec<String>,
+    /// Non-verbose `brew update` only prints "Updated N formulae." instead of the list
+    pub updated_formulae_count: usize,
+    pub updated_casks_count: usize,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl UpdateSummary {
+    pub fn parse(output: &str) -> Self {
+        let mut summary = Self {
+            already_up_to_date: false,
+            updated_taps: Vec::new(),
+            new_formulae: Vec::new(),
+            new_casks: Vec::new(),
+            updated_formulae: Vec::new(),
+            updated_casks: Vec::new(),
+            deleted_formulae: Vec::new(),
+            deleted_casks: Vec::new(),
+            renamed_formulae: HashMap::new(),
+            outdated_formulae: Vec::new(),
+            outdated_casks: Vec::new(),
+            updated_formulae_count: 0,
+            updated_casks_count: 0,
+            updated_at: Utc::now(),
+        };
+        
+        let mut section: Option<String> = None;
+        
+        for line in output.lines() {
+            let line = line.trim();
+            
+            if line.is_empty() {
+                continue;
+            }
+            
+            if let Some(heading) = line.strip_prefix("==> ") {
+                section = Some(heading.trim().to_string());
+                continue;
+            }
+            
+            if line.starts_with("Already up-to-date") {
+                summary.already_up_to_date = true;
+                continue;
+            }
+            
+            // "Updated 2 taps (homebrew/core and homebrew/cask)."
+            if line.starts_with("Updated ") && line.contains(" tap") {
+                if let Some(taps) = line.split_once('(').and_then(|(_, rest)| rest.split_once(')')) {
+                    summary.updated_taps = taps.0
+                        .split(", ")
+                        .flat_map(|part| part.split(" and "))
+                        .map(|tap| tap.trim().to_string())
+                        .filter(|tap| !tap.is_empty())
+                        .collect();
+                }
+                continue;
+            }
+            
+            let Some(heading) = section.as_deref() else {
+                continue;
+            };
+            
+            // "Updated 35 formulae." is printed instead of the list without --verbose
+            if line.starts_with("Updated ") && line.ends_with('.') {
+                let count = line
+                    .split_whitespace()
+                    .nth(1)
+                    .and_then(|count| count.parse::<usize>().ok())
+                    .unwrap_or(0);
+                match heading {
+                    "Updated Formulae" => summary.updated_formulae_count = count,
+                    "Updated Casks" => summary.updated_casks_count = count,
+                    _ => {}
+                }
+                continue;
+            }
+            
+            // "You have 3 outdated formulae installed." is followed by upgrade
+            // hints that don't end in a period, so the rest of the section is prose
+            if line.starts_with("You have ") {
+                section = None;
+                continue;
+            }
+            
+            // New entries come with their description, "name: description"
+            if heading == "New Formulae" || heading == "New Casks" {
+                let target = if heading == "New Formulae" {
+                    &mut summary.new_formulae
+                } else {
+                    &mut summary.new_casks
+                };
+                match line.split_once(':') {
+                    Some((name, _)) => target.push(name.trim().to_string()),
+                    None => target.extend(line.split_whitespace().map(|name| name.to_string())),
+                }
+                continue;
+            }
+            
+            // Other prose, e.g. "The following formulae were renamed:"
+            if line.ends_with('.') || line.ends_with(':') {
+                continue;
+            }
+            
+            if heading == "Renamed Formulae" {
+                if let Some((old_name, new_name)) = line.split_once("->") {
+                    summary.renamed_formulae.insert(old_name.trim().to_string(), new_name.trim().to_string());
+                }
+                continue;
+            }
+            
+            let target = match heading {
+                "Updated Formulae" => &mut summary.updated_formulae,
+                "Updated Casks" => &mut summary.updated_casks,
+                "Deleted Formulae" => &mut summary.deleted_formulae,
+                "Deleted Casks" => &mut summary.deleted_casks,
+                "Outdated Formulae" => &mut summary.outdated_formulae,
+                "Outdated Casks" => &mut summary.outdated_casks,
+                _ => continue,
+            };
+            
+            // Entries are laid out in columns when brew thinks it has a wide terminal
+            target.extend(line.split_whitespace().map(|name| name.to_string()));
+        }
+        
+        summary.updated_formulae_count = summary.updated_formulae_count.max(summary.updated_formulae.len());
+        summary.updated_casks_count = summary.updated_casks_count.max(summary.updated_casks.len());
+        
+        summary
+    }
+    
+    /// Where the last summary is kept so it survives restarts
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("BrewDeck").join("last-update.json"))
+    }
+    
+    /// Reads the last saved summary; `None` when there has been no update yet
+    pub fn load(path: &Path) -> Result<Option<Self>, BrewDeckError> {
+        match std::fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).map(Some).map_err(|e| {
+                BrewDeckError::ParsingError(format!("Invalid update summary {}: {}", path.display(), e))
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+    
+    pub fn save(&self, path: &Path) -> Result<(), BrewDeckError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        
+        let temp_path = path.with_extension("json.tmp");
+        std::fs::write(&temp_path, serde_json::to_vec_pretty(self)?)?;
+        std::fs::rename(&temp_path, path)?;
+        
+        debug!("Saved update summary to {}", path.display());
+        Ok(())
+    }
+}
+
+/// Cheap to clone; clones share the cached environment
+#[derive(Clone)]
 pub struct BrewClient {
     brew_path: String,
+    prefix: PathBuf,
+    environment: Arc<RwLock<Option<BrewEnvironment>>>,
+    collecting_environment: Arc<AtomicBool>,
 }
 
 impl BrewClient {
     pub async fn new() -> Result<Self, BrewDeckError> {
         let brew_path = Self::find_brew_path().await?;
-        info!("Found Homebrew at: {}", brew_path);
+        let prefix = Self::find_prefix(&brew_path);
+        info!("Found Homebrew at: {} (prefix {})", brew_path, prefix.display());
+        
+        Ok(Self {
+            brew_path,
+            prefix,
+            environment: Arc::new(RwLock::new(None)),
+            collecting_environment: Arc::new(AtomicBool::new(false)),
+        })
+    }
+    
+    /// The Homebrew prefix, e.g. `/opt/homebrew`, that holds the Cellar and Caskroom
+    pub fn prefix(&self) -> &Path {
+        &self.prefix
+    }
+    
+    fn find_prefix(brew_path: &str) -> PathBuf {
+        if let Ok(prefix) = std::env::var("HOMEBREW_PREFIX") {
+            if !prefix.is_empty() {
+                return PathBuf::from(prefix);
+            }
+        }
         
-        Ok(Self { brew_path })
+        // `<prefix>/bin/brew`; don't resolve symlinks since Intel installs link
+        // /usr/local/bin/brew into /usr/local/Homebrew
+        Path::new(brew_path)
+            .parent()
+            .and_then(Path::parent)
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| PathBuf::from("/usr/local"))
     }
     
     async fn find_brew_path() -> Result<String, BrewDeckError> {
@@ -139,7 +466,7 @@ impl BrewClient {
         
         if !result.success {
             return Err(BrewDeckError::CommandExecutionFailed(
-                format!("Failed to list installed packages: {}", result.stderr)
+                self.with_environment(format!("Failed to list installed packages: {}", result.stderr)).await
             ));
         }
         
@@ -163,7 +490,7 @@ impl BrewClient {
         
         if !result.success {
             return Err(BrewDeckError::CommandExecutionFailed(
-                format!("Failed to list outdated packages: {}", result.stderr)
+                self.with_environment(format!("Failed to list outdated packages: {}", result.stderr)).await
             ));
         }
         
@@ -189,7 +516,7 @@ impl BrewClient {
             Ok(format!("Successfully installed {}", name))
         } else {
             Err(BrewDeckError::InstallationFailed(
-                format!("Failed to install {}: {}", name, result.stderr)
+                self.with_environment(format!("Failed to install {}: {}", name, result.stderr)).await
             ))
         }
     }
@@ -207,7 +534,7 @@ impl BrewClient {
             Ok(format!("Successfully uninstalled {}", name))
         } else {
             Err(BrewDeckError::UninstallationFailed(
-                format!("Failed to uninstall {}: {}", name, result.stderr)
+                self.with_environment(format!("Failed to uninstall {}: {}", name, result.stderr)).await
             ))
         }
     }
@@ -225,18 +552,25 @@ impl BrewClient {
             Ok(format!("Successfully updated {}", name))
         } else {
             Err(BrewDeckError::UpdateFailed(
-                format!("Failed to update {}: {}", name, result.stderr)
+                self.with_environment(format!("Failed to update {}: {}", name, result.stderr)).await
             ))
         }
     }
     
-    pub async fn update_all(&self, package_type: Option<PackageType>) -> Result<String, BrewDeckError> {
-        let args = match package_type {
+    pub async fn update_all(&self, package_type: Option<PackageType>, greedy: GreedyMode) -> Result<String, BrewDeckError> {
+        let mut args = match package_type {
             Some(PackageType::Formula) => vec!["upgrade".to_string(), "--formula".to_string()],
             Some(PackageType::Cask) => vec!["upgrade".to_string(), "--cask".to_string()],
             None => vec!["upgrade".to_string()],
         };
         
+        // Greedy flags only affect casks, so leave them off formula-only upgrades
+        if package_type != Some(PackageType::Formula) {
+            if let Some(flag) = greedy.flag() {
+                args.push(flag.to_string());
+            }
+        }
+        
         let command = BrewCommand::new(args).with_timeout(Duration::from_secs(1800)); // 30 minutes for bulk update
         let result = self.execute(command).await?;
         
@@ -244,7 +578,28 @@ impl BrewClient {
             Ok("Successfully updated all packages".to_string())
         } else {
             Err(BrewDeckError::UpdateFailed(
-                format!("Failed to update packages: {}", result.stderr)
+                self.with_environment(format!("Failed to update packages: {}", result.stderr)).await
+            ))
+        }
+    }
+    
+    /// Upgrades specific casks with `--greedy`, including ones that update themselves
+    pub async fn upgrade_casks_greedy(&self, tokens: &[String]) -> Result<String, BrewDeckError> {
+        if tokens.is_empty() {
+            return Ok("No casks to upgrade".to_string());
+        }
+        
+        let mut args = vec!["upgrade".to_string(), "--cask".to_string(), "--greedy".to_string()];
+        args.extend(tokens.iter().cloned());
+        
+        let command = BrewCommand::new(args).with_timeout(Duration::from_secs(1800));
+        let result = self.execute(command).await?;
+        
+        if result.success {
+            Ok(format!("Successfully updated {}", tokens.join(", ")))
+        } else {
+            Err(BrewDeckError::UpdateFailed(
+                self.with_environment(format!("Failed to update {}: {}", tokens.join(", "), result.stderr)).await
             ))
         }
     }
@@ -268,6 +623,13 @@ impl BrewClient {
     }
     
     pub async fn search(&self, query: &str, package_type: Option<PackageType>) -> Result<Vec<String>, BrewDeckError> {
+        let results = self.search_with_types(query, package_type).await?;
+        Ok(results.into_iter().map(|(name, _)| name).collect())
+    }
+    
+    /// Like `search`, keeping each result's type. Without a type brew lists
+    /// formulae and casks under `==> Formulae` and `==> Casks` headings.
+    pub async fn search_with_types(&self, query: &str, package_type: Option<PackageType>) -> Result<Vec<(String, PackageType)>, BrewDeckError> {
         let mut args = vec!["search".to_string()];
         
         if let Some(PackageType::Cask) = package_type {
@@ -283,15 +645,21 @@ impl BrewClient {
         
         if !result.success {
             return Err(BrewDeckError::CommandExecutionFailed(
-                format!("Search failed: {}", result.stderr)
+                self.with_environment(format!("Search failed: {}", result.stderr)).await
             ));
         }
         
-        let packages = result.stdout
-            .lines()
-            .map(|line| line.trim().to_string())
-            .filter(|line| !line.is_empty() && !line.starts_with("==>"))
-            .collect();
+        let mut section = package_type.unwrap_or(PackageType::Formula);
+        let mut packages = Vec::new();
+        
+        for line in result.stdout.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            match line.strip_prefix("==>").map(str::trim) {
+                Some("Formulae") => section = PackageType::Formula,
+                Some("Casks") => section = PackageType::Cask,
+                Some(_) => {}
+                None => packages.push((line.to_string(), section)),
+            }
+        }
         
         Ok(packages)
     }
@@ -303,21 +671,111 @@ impl BrewClient {
         Ok(result.stdout)
     }
     
-    pub async fn update_homebrew(&self) -> Result<String, BrewDeckError> {
-        let command = BrewCommand::new(vec!["update".to_string()]);
+    pub async fn update_homebrew(&self) -> Result<UpdateSummary, BrewDeckError> {
+        let command = BrewCommand::new(vec!["update".to_string()]).with_timeout(Duration::from_secs(600));
         let result = self.execute(command).await?;
         
         if result.success {
-            Ok("Homebrew updated successfully".to_string())
+            // The core tap HEAD and Homebrew version may have moved
+            self.invalidate_environment().await;
+            
+            let output = format!("{}\n{}", result.stdout, result.stderr);
+            Ok(UpdateSummary::parse(&output))
         } else {
             Err(BrewDeckError::UpdateFailed(
-                format!("Failed to update Homebrew: {}", result.stderr)
+                self.with_environment(format!("Failed to update Homebrew: {}", result.stderr)).await
             ))
         }
     }
+    
+    pub async fn get_brew_environment(&self) -> Result<BrewEnvironment, BrewDeckError> {
+        if let Some(environment) = self.environment.read().await.as_ref() {
+            return Ok(environment.clone());
+        }
+        
+        let command = BrewCommand::new(vec!["config".to_string()]).with_timeout(Duration::from_secs(60));
+        let config = self.execute(command).await?;
+        if !config.success {
+            return Err(BrewDeckError::CommandExecutionFailed(
+                format!("Failed to read Homebrew config: {}", config.stderr)
+            ));
+        }
+        
+        let command = BrewCommand::new(vec!["--version".to_string()]).with_timeout(Duration::from_secs(30));
+        let version = self.execute(command).await?;
+        if !version.success {
+            return Err(BrewDeckError::CommandExecutionFailed(
+                format!("Failed to read Homebrew version: {}", version.stderr)
+            ));
+        }
+        
+        let environment = BrewEnvironment::parse(&config.stdout, &version.stdout);
+        debug!("Collected Homebrew environment: {}", environment.summary());
+        
+        *self.environment.write().await = Some(environment.clone());
+        Ok(environment)
+    }
+    
+    pub async fn invalidate_environment(&self) {
+        *self.environment.write().await = None;
+    }
+    
+    /// Attaches the Homebrew environment to an error message so failure reports
+    /// carry enough context to debug someone else's machine.
+    ///
+    /// Only the cached environment is used: running `brew config` here would
+    /// hold up every error by seconds. On a cache miss it is collected in the
+    /// background so later reports have it.
+    async fn with_environment(&self, message: String) -> String {
+        if let Some(environment) = self.environment.read().await.as_ref() {
+            return format!("{message}\n\nEnvironment: {}", environment.summary());
+        }
+        
+        self.collect_environment_in_background();
+        message
+    }
+    
+    fn collect_environment_in_background(&self) {
+        if self.collecting_environment.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        
+        let client = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = client.get_brew_environment().await {
+                debug!("Homebrew environment unavailable for error reports: {}", e);
+            }
+            client.collecting_environment.store(false, Ordering::Release);
+        });
+    }
+}
+
+/// Which casks `brew upgrade --cask` should consider beyond the ones brew knows are outdated
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, Eq, PartialEq)]
+pub enum GreedyMode {
+    /// Skip casks with `auto_updates true` or `version :latest`
+    #[default]
+    Off,
+    /// `--greedy`: upgrade every cask
+    All,
+    /// `--greedy-auto-updates`: include casks that update themselves
+    AutoUpdates,
+    /// `--greedy-latest`: include casks with `version :latest`
+    Latest,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, Hash, PartialEq)]
+impl GreedyMode {
+    pub fn flag(&self) -> Option<&'static str> {
+        match self {
+            GreedyMode::Off => None,
+            GreedyMode::All => Some("--greedy"),
+            GreedyMode::AutoUpdates => Some("--greedy-auto-updates"),
+            GreedyMode::Latest => Some("--greedy-latest"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, Hash, PartialEq, Ord, PartialOrd)]
 pub enum PackageType {
     Formula,
     Cask,
@@ -344,4 +802,156 @@ impl std::str::FromStr for PackageType {
             )),
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const BREW_CONFIG: &str = "\
+HOMEBREW_VERSION: 4.3.5
+ORIGIN: https://github.com/Homebrew/brew
+HEAD: 2a4d3b5a1c0e8f7d6b5a4c3d2e1f0a9b8c7d6e5f
+Last commit: 3 days ago
+Core tap JSON: 12 Jun 09:14 UTC
+HOMEBREW_PREFIX: /opt/homebrew
+HOMEBREW_CASK_OPTS: []
+HOMEBREW_MAKE_JOBS: 10
+HOMEBREW_NO_AUTO_UPDATE: set
+Homebrew Ruby: 3.3.3 => /opt/homebrew/Library/Homebrew/vendor/portable-ruby/3.3.3/bin/ruby
+CPU: deca-core 64-bit arm_firestorm_icestorm
+Clang: 15.0.0 build 1500
+Git: 2.39.3 => /Library/Developer/CommandLineTools/usr/bin/git
+Curl: 8.6.0 => /usr/bin/curl
+macOS: 14.5-arm64
+CLT: 15.3.0.0.1.1708646388
+Xcode: N/A
+Rosetta 2: false
+";
+    
+    #[test]
+    fn parses_brew_config() {
+        let environment = BrewEnvironment::parse(BREW_CONFIG, "Homebrew 4.3.5\n");
+        
+        assert_eq!(environment.homebrew_version, "4.3.5");
+        assert_eq!(environment.origin.as_deref(), Some("https://github.com/Homebrew/brew"));
+        assert_eq!(environment.last_commit.as_deref(), Some("3 days ago"));
+        assert_eq!(environment.core_tap_json.as_deref(), Some("12 Jun 09:14 UTC"));
+        assert_eq!(environment.prefix.as_deref(), Some("/opt/homebrew"));
+        assert_eq!(environment.cpu.as_deref(), Some("deca-core 64-bit arm_firestorm_icestorm"));
+        assert_eq!(environment.os.as_deref(), Some("14.5-arm64"));
+        assert_eq!(environment.clt.as_deref(), Some("15.3.0.0.1.1708646388"));
+        assert_eq!(environment.rosetta, Some(false));
+        assert!(environment.ruby.as_deref().is_some_and(|ruby| ruby.starts_with("3.3.3 => ")));
+    }
+    
+    #[test]
+    fn not_installed_tools_are_none() {
+        let environment = BrewEnvironment::parse(BREW_CONFIG, "Homebrew 4.3.5\n");
+        
+        assert_eq!(environment.xcode, None);
+        assert_eq!(environment.raw.get("Xcode").map(String::as_str), Some("N/A"));
+    }
+    
+    #[test]
+    fn env_overrides_exclude_reported_settings() {
+        let environment = BrewEnvironment::parse(BREW_CONFIG, "Homebrew 4.3.5\n");
+        
+        let mut overrides: Vec<_> = environment.env_overrides.keys().map(String::as_str).collect();
+        overrides.sort();
+        assert_eq!(overrides, ["HOMEBREW_CASK_OPTS", "HOMEBREW_MAKE_JOBS", "HOMEBREW_NO_AUTO_UPDATE"]);
+        assert!(environment.summary().ends_with(
+            "overrides HOMEBREW_CASK_OPTS,HOMEBREW_MAKE_JOBS,HOMEBREW_NO_AUTO_UPDATE"
+        ));
+    }
+    
+    #[test]
+    fn version_falls_back_to_config() {
+        let environment = BrewEnvironment::parse(BREW_CONFIG, "");
+        assert_eq!(environment.homebrew_version, "4.3.5");
+        
+        let environment = BrewEnvironment::parse("", "");
+        assert_eq!(environment.homebrew_version, "unknown");
+        assert_eq!(environment.prefix, None);
+    }
+    
+    const BREW_UPDATE: &str = "\
+==> Updating Homebrew...
+Updated 2 taps (homebrew/core and homebrew/cask).
+==> New Formulae
+aider: AI pair programming in your terminal
+mise: Polyglot runtime manager (asdf rust clone).
+==> New Casks
+cursor: Write, edit, and chat about your code with AI
+==> Updated Formulae
+Updated 35 formulae.
+==> Renamed Formulae
+youtube-dl -> yt-dlp
+==> Deleted Formulae
+vault
+==> Outdated Formulae
+git                                      node
+
+You have 2 outdated formulae installed.
+You can upgrade them with brew upgrade
+or list them with brew outdated.
+";
+    
+    #[test]
+    fn parses_brew_update() {
+        let summary = UpdateSummary::parse(BREW_UPDATE);
+        
+        assert!(!summary.already_up_to_date);
+        assert_eq!(summary.updated_taps, ["homebrew/core", "homebrew/cask"]);
+        assert_eq!(summary.new_formulae, ["aider", "mise"]);
+        assert_eq!(summary.new_casks, ["cursor"]);
+        assert!(summary.updated_formulae.is_empty());
+        assert_eq!(summary.updated_formulae_count, 35);
+        assert_eq!(summary.renamed_formulae.get("youtube-dl").map(String::as_str), Some("yt-dlp"));
+        assert_eq!(summary.deleted_formulae, ["vault"]);
+        assert_eq!(summary.outdated_formulae, ["git", "node"]);
+    }
+    
+    #[test]
+    fn parses_verbose_brew_update() {
+        let output = "\
+Updated 1 tap (homebrew/cask).
+==> Updated Casks
+Updated 3 casks.
+firefox                    google-chrome
+slack
+==> Deleted Casks
+atom
+";
+        let summary = UpdateSummary::parse(output);
+        
+        assert_eq!(summary.updated_taps, ["homebrew/cask"]);
+        assert_eq!(summary.updated_casks, ["firefox", "google-chrome", "slack"]);
+        assert_eq!(summary.updated_casks_count, 3);
+        assert_eq!(summary.deleted_casks, ["atom"]);
+    }
+    
+    #[test]
+    fn parses_up_to_date() {
+        let summary = UpdateSummary::parse("Already up-to-date.\n");
+        
+        assert!(summary.already_up_to_date);
+        assert!(summary.updated_taps.is_empty());
+        assert!(summary.new_formulae.is_empty());
+    }
+    
+    #[test]
+    fn update_summary_round_trips() {
+        let dir = std::env::temp_dir().join(format!("brewdeck-update-summary-{}", std::process::id()));
+        let path = dir.join("last-update.json");
+        
+        assert!(UpdateSummary::load(&path).unwrap().is_none());
+        
+        let summary = UpdateSummary::parse(BREW_UPDATE);
+        summary.save(&path).unwrap();
+        let loaded = UpdateSummary::load(&path).unwrap().unwrap();
+        assert_eq!(loaded.new_formulae, summary.new_formulae);
+        assert_eq!(loaded.updated_at, summary.updated_at);
+        
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
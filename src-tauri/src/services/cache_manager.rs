@@ -2,6 +2,7 @@ use crate::error::BrewDeckError;
 use chrono::{DateTime, Utc};
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::RwLock;
@@ -76,6 +77,20 @@ impl Default for CacheConfig {
     }
 }
 
+impl CacheConfig {
+    /// Directory for data kept across restarts, or `None` when persistence is off
+    pub fn persistence_dir(&self) -> Option<PathBuf> {
+        if !self.persistence_enabled {
+            return None;
+        }
+        
+        match &self.persistence_path {
+            Some(path) => Some(PathBuf::from(path)),
+            None => dirs::cache_dir().map(|dir| dir.join("BrewDeck")),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum EvictionStrategy {
     LeastRecentlyUsed,
@@ -173,6 +188,10 @@ impl CacheManager {
         Ok(())
     }
     
+    pub fn config(&self) -> &CacheConfig {
+        &self.config
+    }
+    
     pub async fn invalidate(&self, key: &str) -> bool {
         match self.storage.remove(key) {
             Some(_) => {
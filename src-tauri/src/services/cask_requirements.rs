@@ -0,0 +1,309 @@
+use crate::services::api_client::{ApiArchRequirement, ApiCaskResponse, ApiDependsOn};
+use crate::services::platform::{HostArch, HostPlatform, MacOsVersion};
+use serde::Deserialize;
+use tracing::debug;
+
+/// The fields a cask's `variations` entry can override for one platform
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ApiCaskVariation {
+    url: Option<String>,
+    version: Option<String>,
+    sha256: Option<String>,
+    artifacts: Option<Vec<serde_json::Value>>,
+    caveats: Option<String>,
+    depends_on: Option<ApiDependsOn>,
+    conflicts_with: Option<serde_json::Value>,
+    container: Option<serde_json::Value>,
+    auto_updates: Option<bool>,
+}
+
+/// Applies the `variations` entry for `host`'s bottle tag, the way brew does
+/// when it loads a cask from the API. Returns whether one applied.
+pub fn apply_variation(cask: &mut ApiCaskResponse, host: &HostPlatform) -> bool {
+    let Some(tag) = host.bottle_tag() else {
+        return false;
+    };
+    let Some(variation) = cask.variations.remove(&tag) else {
+        return false;
+    };
+
+    let variation: ApiCaskVariation = match serde_json::from_value(variation) {
+        Ok(variation) => variation,
+        Err(e) => {
+            debug!("Ignoring malformed {} variation of {}: {}", tag, cask.token, e);
+            return false;
+        }
+    };
+
+    if let Some(url) = variation.url {
+        cask.url = url;
+    }
+    if let Some(version) = variation.version {
+        cask.version = version;
+    }
+    if let Some(sha256) = variation.sha256 {
+        cask.sha256 = sha256;
+    }
+    if let Some(artifacts) = variation.artifacts {
+        cask.artifacts = artifacts;
+    }
+    if let Some(caveats) = variation.caveats {
+        cask.caveats = Some(caveats);
+    }
+    if let Some(depends_on) = variation.depends_on {
+        cask.depends_on = depends_on;
+    }
+    if let Some(conflicts_with) = variation.conflicts_with {
+        cask.conflicts_with = Some(conflicts_with);
+    }
+    if let Some(container) = variation.container {
+        cask.container = Some(container);
+    }
+    if let Some(auto_updates) = variation.auto_updates {
+        cask.auto_updates = Some(auto_updates);
+    }
+
+    true
+}
+
+/// Describes every `depends_on` macOS or architecture constraint `host`
+/// does not meet; empty when the cask can be installed. Constraints that
+/// cannot be evaluated, e.g. an undetected macOS version, are not reported.
+pub fn unmet_requirements(depends_on: &ApiDependsOn, host: &HostPlatform) -> Vec<String> {
+    let mut unmet = Vec::new();
+
+    if let Some(macos) = &depends_on.macos {
+        if !host.is_macos() {
+            unmet.push("Requires macOS".to_string());
+        } else if let Some(version) = host.macos_version {
+            for (operator, values) in macos {
+                if let Some(message) = unmet_macos_requirement(operator, values, version) {
+                    unmet.push(message);
+                }
+            }
+        }
+    }
+
+    if let Some(arches) = depends_on.arch.as_ref().filter(|arches| !arches.is_empty()) {
+        if !arches.iter().any(|arch| arch_matches(arch, host.arch)) {
+            let required: Vec<&str> = arches.iter().map(|arch| arch_label(&arch.arch_type)).collect();
+            unmet.push(format!("Requires {}; this machine is {}", required.join(" or "), host_arch_label(host.arch)));
+        }
+    }
+
+    unmet
+}
+
+fn unmet_macos_requirement(operator: &str, values: &[String], host: MacOsVersion) -> Option<String> {
+    let required: Vec<MacOsVersion> = values
+        .iter()
+        .filter_map(|value| MacOsVersion::parse_requirement(value))
+        .collect();
+    let host_release = host.release();
+
+    let satisfied = match (operator, required.first()) {
+        ("==", Some(_)) => required.iter().any(|version| version.release() == host_release),
+        (">=", Some(version)) => host_release >= version.release(),
+        (">", Some(version)) => host_release > version.release(),
+        ("<=", Some(version)) => host_release <= version.release(),
+        ("<", Some(version)) => host_release < version.release(),
+        _ => {
+            debug!("Unsupported macOS requirement {} {:?}", operator, values);
+            return None;
+        }
+    };
+
+    if satisfied {
+        return None;
+    }
+
+    let names: Vec<String> = required.iter().map(|version| release_label(*version)).collect();
+    let requirement = match operator {
+        "==" => names.join(" or "),
+        ">=" => format!("{} or newer", names[0]),
+        ">" => format!("newer than {}", names[0]),
+        "<=" => format!("{} or older", names[0]),
+        _ => format!("older than {}", names[0]),
+    };
+
+    Some(format!("Requires macOS {requirement}; this Mac runs {}", release_label(host)))
+}
+
+/// `14 (sonoma)` or `10.15 (catalina)`
+fn release_label(version: MacOsVersion) -> String {
+    let (major, minor) = version.release();
+    let number = if major >= 11 { major.to_string() } else { format!("{major}.{minor}") };
+
+    // Releases newer than the ones we know have no codename yet
+    let codename = version
+        .codename()
+        .filter(|codename| MacOsVersion::parse_requirement(codename).map(MacOsVersion::release) == Some((major, minor)));
+
+    match codename {
+        Some(codename) => format!("{number} ({codename})"),
+        None => number,
+    }
+}
+
+/// brew writes `arm` for Apple silicon and `intel` for x86_64
+fn arch_matches(requirement: &ApiArchRequirement, arch: HostArch) -> bool {
+    matches!(
+        (requirement.arch_type.as_str(), arch),
+        ("arm" | "arm64", HostArch::Arm64) | ("intel" | "x86_64", HostArch::X86_64)
+    )
+}
+
+fn arch_label(arch_type: &str) -> &str {
+    match arch_type {
+        "arm" | "arm64" => "Apple silicon",
+        "intel" | "x86_64" => "an Intel processor",
+        other => other,
+    }
+}
+
+fn host_arch_label(arch: HostArch) -> &'static str {
+    match arch {
+        HostArch::Arm64 => "arm64",
+        HostArch::X86_64 => "x86_64",
+        HostArch::Other => "an unsupported architecture",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::api_client::PackageParser;
+    use crate::services::platform::HostOs;
+    use serde_json::json;
+
+    fn mac(arch: HostArch, version: &str) -> HostPlatform {
+        HostPlatform::new(HostOs::MacOs, arch, MacOsVersion::parse(version))
+    }
+
+    fn depends_on(value: serde_json::Value) -> ApiDependsOn {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn cask(value: serde_json::Value) -> ApiCaskResponse {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn reports_macos_release_below_minimum() {
+        let host = mac(HostArch::Arm64, "14.5");
+        let unmet = unmet_requirements(&depends_on(json!({"macos": {">=": ["15"]}})), &host);
+
+        assert_eq!(unmet, vec!["Requires macOS 15 (sequoia) or newer; this Mac runs 14 (sonoma)"]);
+    }
+
+    #[test]
+    fn accepts_point_release_of_required_release() {
+        let host = mac(HostArch::Arm64, "15.3");
+
+        assert!(unmet_requirements(&depends_on(json!({"macos": {">=": ["15"]}})), &host).is_empty());
+        assert!(unmet_requirements(&depends_on(json!({"macos": {"==": ["big_sur", "sequoia"]}})), &host).is_empty());
+    }
+
+    #[test]
+    fn compares_pre_big_sur_minor_releases() {
+        let host = mac(HostArch::X86_64, "10.14.6");
+        let unmet = unmet_requirements(&depends_on(json!({"macos": {">=": ["10.15"]}})), &host);
+
+        assert_eq!(unmet, vec!["Requires macOS 10.15 (catalina) or newer; this Mac runs 10.14 (mojave)"]);
+    }
+
+    #[test]
+    fn reports_intel_only_cask_on_apple_silicon() {
+        let requirement = depends_on(json!({"arch": [{"type": "intel", "bits": 64}]}));
+
+        assert_eq!(
+            unmet_requirements(&requirement, &mac(HostArch::Arm64, "14.5")),
+            vec!["Requires an Intel processor; this machine is arm64"]
+        );
+        assert!(unmet_requirements(&requirement, &mac(HostArch::X86_64, "14.5")).is_empty());
+    }
+
+    #[test]
+    fn skips_macos_requirement_when_version_is_unknown() {
+        let host = HostPlatform::new(HostOs::MacOs, HostArch::Arm64, None);
+
+        assert!(unmet_requirements(&depends_on(json!({"macos": {">=": ["15"]}})), &host).is_empty());
+    }
+
+    #[test]
+    fn requires_macos_on_linux() {
+        let host = HostPlatform::new(HostOs::Linux, HostArch::X86_64, None);
+
+        assert_eq!(
+            unmet_requirements(&depends_on(json!({"macos": {">=": ["12"]}})), &host),
+            vec!["Requires macOS"]
+        );
+    }
+
+    #[test]
+    fn applies_variation_for_host_tag() {
+        let mut cask = cask(json!({
+            "token": "example",
+            "version": "2.0",
+            "url": "https://example.com/arm.dmg",
+            "depends_on": {"macos": {">=": ["14"]}},
+            "variations": {
+                "sonoma": {
+                    "url": "https://example.com/intel.dmg",
+                    "version": "1.9",
+                    "depends_on": {"macos": {">=": ["13"]}}
+                },
+                "arm64_ventura": {"version": "1.5"}
+            }
+        }));
+
+        assert!(apply_variation(&mut cask, &mac(HostArch::X86_64, "14.5")));
+        assert_eq!(cask.url, "https://example.com/intel.dmg");
+        assert_eq!(cask.version, "1.9");
+        assert!(unmet_requirements(&cask.depends_on, &mac(HostArch::X86_64, "13.6")).is_empty());
+    }
+
+    #[test]
+    fn keeps_cask_without_variation_for_host() {
+        let mut cask = cask(json!({
+            "token": "example",
+            "version": "2.0",
+            "variations": {"arm64_ventura": {"version": "1.5"}}
+        }));
+
+        assert!(!apply_variation(&mut cask, &mac(HostArch::Arm64, "14.5")));
+        assert!(!apply_variation(&mut cask, &HostPlatform::new(HostOs::Linux, HostArch::X86_64, None)));
+        assert_eq!(cask.version, "2.0");
+    }
+
+    #[test]
+    fn ignores_malformed_variation() {
+        let mut cask = cask(json!({
+            "token": "example",
+            "version": "2.0",
+            "variations": {"arm64_sonoma": {"version": 3}}
+        }));
+
+        assert!(!apply_variation(&mut cask, &mac(HostArch::Arm64, "14.5")));
+        assert_eq!(cask.version, "2.0");
+    }
+
+    #[test]
+    fn parses_cask_for_given_host() {
+        let entry = json!({
+            "token": "example",
+            "version": "2.0",
+            "depends_on": {"macos": {">=": ["15"]}},
+            "variations": {"arm64_sonoma": {"version": "1.9"}}
+        });
+
+        let sonoma = PackageParser::parse_cask_to_package_info(cask(entry.clone()), &mac(HostArch::Arm64, "14.5"));
+        assert_eq!(sonoma.version, "1.9");
+        assert_eq!(sonoma.unmet_requirements.len(), 1);
+
+        let sequoia = PackageParser::parse_cask_to_package_info(cask(entry), &mac(HostArch::Arm64, "15.1"));
+        assert_eq!(sequoia.version, "2.0");
+        assert!(sequoia.unmet_requirements.is_empty());
+    }
+}
@@ -0,0 +1,207 @@
+use crate::services::api_client::ApiPackageInfo;
+use crate::services::brew_client::PackageType;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::debug;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RedirectKind {
+    /// `python` -> `python@3.12`
+    Alias,
+    /// A formula's `oldnames` or a cask's `old_tokens`, left behind by renames
+    OldName,
+    /// A tap-qualified name such as `homebrew/core/wget`
+    FullName,
+}
+
+/// Reported on a package when it was looked up by something other than its
+/// canonical name.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackageRedirect {
+    pub requested: String,
+    pub canonical: String,
+    pub kind: RedirectKind,
+}
+
+/// One parsed catalog with every identifier brew accepts mapped to its entry.
+///
+/// Canonical names always win over redirects, since an old name can be reused
+/// by a different package after a rename.
+pub struct CatalogIndex {
+    packages: Vec<ApiPackageInfo>,
+    by_name: HashMap<String, usize>,
+    redirects: HashMap<String, (usize, RedirectKind)>,
+}
+
+impl CatalogIndex {
+    pub fn build(package_type: PackageType, packages: Vec<ApiPackageInfo>) -> Self {
+        let start_time = std::time::Instant::now();
+        let mut by_name = HashMap::with_capacity(packages.len());
+        let mut redirects = HashMap::new();
+
+        for (position, info) in packages.iter().enumerate() {
+            by_name.insert(canonical_name(info).to_lowercase(), position);
+        }
+
+        for (position, info) in packages.iter().enumerate() {
+            let identifiers = info.aliases.iter().map(|alias| (alias, RedirectKind::Alias))
+                .chain(info.old_names.iter().map(|old| (old, RedirectKind::OldName)))
+                .chain(std::iter::once((&info.full_name, RedirectKind::FullName)));
+
+            for (identifier, kind) in identifiers {
+                let key = identifier.to_lowercase();
+                if key.is_empty() || by_name.contains_key(&key) {
+                    continue;
+                }
+                redirects.entry(key).or_insert((position, kind));
+            }
+        }
+
+        debug!(
+            "Indexed {} {} packages with {} redirects in {:?}",
+            packages.len(),
+            package_type,
+            redirects.len(),
+            start_time.elapsed()
+        );
+
+        Self {
+            packages,
+            by_name,
+            redirects,
+        }
+    }
+
+    pub fn packages(&self) -> &[ApiPackageInfo] {
+        &self.packages
+    }
+
+    /// The package with this canonical name, ignoring redirects
+    pub fn get(&self, name: &str) -> Option<&ApiPackageInfo> {
+        self.by_name
+            .get(&name.to_lowercase())
+            .map(|&position| &self.packages[position])
+    }
+
+    /// Looks up a name, token, alias, old name or tap-qualified name, along
+    /// with the redirect taken to reach the canonical entry.
+    pub fn resolve(&self, identifier: &str) -> Option<(&ApiPackageInfo, Option<PackageRedirect>)> {
+        let key = identifier.trim().to_lowercase();
+
+        if let Some(&position) = self.by_name.get(&key) {
+            return Some((&self.packages[position], None));
+        }
+
+        let &(position, kind) = self.redirects.get(&key)?;
+        let info = &self.packages[position];
+        let redirect = PackageRedirect {
+            requested: identifier.to_string(),
+            canonical: canonical_name(info).to_string(),
+            kind,
+        };
+
+        Some((info, Some(redirect)))
+    }
+
+    /// The canonical name for `identifier`, or `identifier` itself when the
+    /// catalog does not know it (e.g. a formula from an untapped tap).
+    pub fn canonical<'a>(&'a self, identifier: &'a str) -> &'a str {
+        self.resolve(identifier)
+            .map(|(info, _)| canonical_name(info))
+            .unwrap_or(identifier)
+    }
+}
+
+/// Casks are identified by token; `name` is the app's display name
+pub fn canonical_name(info: &ApiPackageInfo) -> &str {
+    info.token.as_deref().unwrap_or(&info.name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::api_client::PackageParser;
+    use crate::services::platform::HostPlatform;
+    use serde_json::json;
+
+    fn formula(value: serde_json::Value) -> ApiPackageInfo {
+        PackageParser::parse_formula_to_package_info(serde_json::from_value(value).unwrap(), HostPlatform::current())
+    }
+
+    fn catalog() -> CatalogIndex {
+        CatalogIndex::build(
+            PackageType::Formula,
+            vec![
+                formula(json!({
+                    "name": "python@3.12",
+                    "full_name": "python@3.12",
+                    "aliases": ["python", "python3"]
+                })),
+                // Its own canonical name beats the alias above
+                formula(json!({"name": "python3", "full_name": "python3"})),
+                formula(json!({
+                    "name": "gnupg",
+                    "full_name": "gnupg",
+                    "oldname": "gpg2",
+                    "oldnames": ["gpg"]
+                })),
+                formula(json!({"name": "wget", "full_name": "homebrew/core/wget"})),
+            ],
+        )
+    }
+
+    #[test]
+    fn canonical_name_beats_alias() {
+        let index = catalog();
+
+        let (info, redirect) = index.resolve("python3").unwrap();
+        assert_eq!(info.name, "python3");
+        assert_eq!(redirect, None);
+
+        let (info, redirect) = index.resolve("Python").unwrap();
+        assert_eq!(info.name, "python@3.12");
+        assert_eq!(
+            redirect,
+            Some(PackageRedirect {
+                requested: "Python".to_string(),
+                canonical: "python@3.12".to_string(),
+                kind: RedirectKind::Alias,
+            })
+        );
+    }
+
+    #[test]
+    fn resolves_renamed_formula_by_old_names() {
+        let index = catalog();
+
+        for old_name in ["gpg", "gpg2"] {
+            let (info, redirect) = index.resolve(old_name).unwrap();
+            assert_eq!(info.name, "gnupg");
+            assert_eq!(redirect.map(|redirect| redirect.kind), Some(RedirectKind::OldName));
+        }
+        assert_eq!(index.canonical("gpg"), "gnupg");
+    }
+
+    #[test]
+    fn resolves_tap_qualified_name() {
+        let index = catalog();
+
+        let (info, redirect) = index.resolve(" homebrew/core/wget ").unwrap();
+        assert_eq!(info.name, "wget");
+        let redirect = redirect.unwrap();
+        assert_eq!(redirect.kind, RedirectKind::FullName);
+        assert_eq!(redirect.canonical, "wget");
+    }
+
+    #[test]
+    fn leaves_unknown_names_alone() {
+        let index = catalog();
+
+        assert!(index.resolve("user/tools/wget2").is_none());
+        assert_eq!(index.canonical("user/tools/wget2"), "user/tools/wget2");
+        // `get` ignores redirects
+        assert!(index.get("python").is_none());
+        assert!(index.get("PYTHON3").is_some());
+    }
+}
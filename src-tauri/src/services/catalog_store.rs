@@ -0,0 +1,191 @@
+use crate::error::BrewDeckError;
+use crate::services::brew_client::PackageType;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+/// Validators and bookkeeping stored next to a downloaded catalog body
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogMetadata {
+    pub package_type: PackageType,
+    pub source_url: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// When the body was last downloaded
+    pub fetched_at: DateTime<Utc>,
+    /// When the server last confirmed the body, by a 200 or a 304
+    pub validated_at: DateTime<Utc>,
+    pub size_bytes: u64,
+    /// Key id the body's signature was verified with; `None` for unsigned catalogs
+    #[serde(default)]
+    pub verified_by: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogStatus {
+    pub package_type: PackageType,
+    pub stored: bool,
+    pub source_url: Option<String>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub fetched_at: Option<DateTime<Utc>>,
+    pub validated_at: Option<DateTime<Utc>>,
+    /// Seconds since the catalog was last validated against the server
+    pub age_seconds: Option<u64>,
+    pub size_bytes: u64,
+    /// Filled in once the catalog has been parsed in this session
+    #[serde(default)]
+    pub last_parse: Option<CatalogParseStats>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogParseStats {
+    pub bytes: u64,
+    pub records: usize,
+    pub duration_ms: u64,
+    pub parsed_at: DateTime<Utc>,
+}
+
+/// A catalog on disk, opened for streaming rather than read into memory
+pub struct StoredCatalog {
+    pub metadata: CatalogMetadata,
+    pub body: std::fs::File,
+}
+
+/// Keeps the last downloaded `formula.json` / `cask.json` on disk so that
+/// refreshes can be conditional requests instead of full downloads.
+pub struct CatalogStore {
+    dir: PathBuf,
+}
+
+impl CatalogStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn body_path(&self, package_type: PackageType) -> PathBuf {
+        self.dir.join(format!("{package_type}.json"))
+    }
+
+    fn metadata_path(&self, package_type: PackageType) -> PathBuf {
+        self.dir.join(format!("{package_type}.meta.json"))
+    }
+
+    /// Where the search index built from the catalog is kept
+    pub fn search_index_path(&self, package_type: PackageType) -> PathBuf {
+        self.dir.join(format!("{package_type}.search.json"))
+    }
+
+    pub async fn load_metadata(&self, package_type: PackageType) -> Option<CatalogMetadata> {
+        let path = self.metadata_path(package_type);
+        let contents = tokio::fs::read(&path).await.ok()?;
+
+        match serde_json::from_slice(&contents) {
+            Ok(metadata) => Some(metadata),
+            Err(e) => {
+                warn!("Ignoring unreadable catalog metadata {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Returns the stored catalog only when both the body and its metadata are
+    /// present and agree on the size, so a torn write is never served.
+    pub async fn load(&self, package_type: PackageType) -> Option<StoredCatalog> {
+        let metadata = self.load_metadata(package_type).await?;
+        let body = tokio::fs::File::open(self.body_path(package_type)).await.ok()?;
+        let size_bytes = body.metadata().await.ok()?.len();
+
+        if size_bytes != metadata.size_bytes {
+            warn!(
+                "Stored {} catalog is {} bytes but metadata expects {}; discarding",
+                package_type,
+                size_bytes,
+                metadata.size_bytes
+            );
+            return None;
+        }
+
+        Some(StoredCatalog {
+            metadata,
+            body: body.into_std().await,
+        })
+    }
+
+    /// Where a download is streamed to before `save_download` moves it into
+    /// place
+    pub async fn download_path(&self, package_type: PackageType) -> Result<PathBuf, BrewDeckError> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        Ok(self.body_path(package_type).with_extension("json.tmp"))
+    }
+
+    /// Moves a download from `download_path` into place and records its metadata
+    pub async fn save_download(&self, metadata: &CatalogMetadata, download: &Path) -> Result<(), BrewDeckError> {
+        // Body first, metadata last: metadata pointing at a stale body is caught
+        // by the size check in `load`
+        tokio::fs::rename(download, self.body_path(metadata.package_type)).await?;
+        write_atomic(&self.metadata_path(metadata.package_type), &serde_json::to_vec_pretty(metadata)?).await?;
+
+        debug!(
+            "Stored {} catalog ({} bytes) in {}",
+            metadata.package_type,
+            metadata.size_bytes,
+            self.dir.display()
+        );
+        Ok(())
+    }
+
+    /// Records a 304 from the server without touching the body
+    pub async fn mark_validated(&self, package_type: PackageType) -> Result<Option<CatalogMetadata>, BrewDeckError> {
+        let Some(mut metadata) = self.load_metadata(package_type).await else {
+            return Ok(None);
+        };
+
+        metadata.validated_at = Utc::now();
+        write_atomic(&self.metadata_path(package_type), &serde_json::to_vec_pretty(&metadata)?).await?;
+
+        Ok(Some(metadata))
+    }
+
+    pub async fn status(&self, package_type: PackageType) -> CatalogStatus {
+        match self.load_metadata(package_type).await {
+            Some(metadata) => CatalogStatus {
+                package_type,
+                stored: true,
+                age_seconds: Some((Utc::now() - metadata.validated_at).num_seconds().max(0) as u64),
+                source_url: Some(metadata.source_url),
+                etag: metadata.etag,
+                last_modified: metadata.last_modified,
+                fetched_at: Some(metadata.fetched_at),
+                validated_at: Some(metadata.validated_at),
+                size_bytes: metadata.size_bytes,
+                last_parse: None,
+            },
+            None => Self::unavailable_status(package_type),
+        }
+    }
+
+    /// Status for a catalog that has never been stored
+    pub fn unavailable_status(package_type: PackageType) -> CatalogStatus {
+        CatalogStatus {
+            package_type,
+            stored: false,
+            source_url: None,
+            etag: None,
+            last_modified: None,
+            fetched_at: None,
+            validated_at: None,
+            age_seconds: None,
+            size_bytes: 0,
+            last_parse: None,
+        }
+    }
+}
+
+async fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), BrewDeckError> {
+    let tmp_path = path.with_extension("json.tmp");
+    tokio::fs::write(&tmp_path, contents).await?;
+    tokio::fs::rename(&tmp_path, path).await?;
+    Ok(())
+}
@@ -0,0 +1,178 @@
+use crate::error::BrewDeckError;
+use crate::services::brew_client::PackageType;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+/// Whether a catalog is current or the last good snapshot served while offline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogFreshness {
+    pub package_type: PackageType,
+    pub stale: bool,
+    /// When the server last confirmed the data
+    pub as_of: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OfflineStatus {
+    pub offline: bool,
+    /// Set through `set_offline_mode`; stays until turned off
+    pub manual: bool,
+    /// Set after a network or timeout error; cleared once a probe succeeds
+    pub network_unreachable: bool,
+    pub offline_since: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    /// Catalogs to refresh as soon as the network is back
+    pub pending_refreshes: Vec<PackageType>,
+    pub catalogs: Vec<CatalogFreshness>,
+}
+
+#[derive(Debug, Default)]
+struct ConnectivityState {
+    manual: bool,
+    network_unreachable: bool,
+    offline_since: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+    pending_refreshes: HashSet<PackageType>,
+}
+
+/// Tracks whether BrewDeck should stay off the network, either because the
+/// user asked to or because requests have been failing.
+#[derive(Default)]
+pub struct ConnectivityMonitor {
+    state: RwLock<ConnectivityState>,
+}
+
+impl ConnectivityMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Errors that mean the network is unreachable, as opposed to the API
+    /// answering with an error status or bad data
+    pub fn indicates_offline(error: &BrewDeckError) -> bool {
+        matches!(error, BrewDeckError::NetworkError(_) | BrewDeckError::TimeoutError(_))
+    }
+
+    /// Errors after which the last good snapshot is worth serving: the
+    /// network is down, or the server is failing without being unreachable
+    pub fn can_serve_snapshot(error: &BrewDeckError) -> bool {
+        Self::indicates_offline(error) || matches!(error, BrewDeckError::HttpStatusError(_, status) if *status >= 500)
+    }
+
+    pub async fn is_offline(&self) -> bool {
+        let state = self.state.read().await;
+        state.manual || state.network_unreachable
+    }
+
+    pub async fn is_network_unreachable(&self) -> bool {
+        self.state.read().await.network_unreachable
+    }
+
+    pub async fn set_manual(&self, offline: bool) {
+        let mut state = self.state.write().await;
+        state.manual = offline;
+
+        if offline && state.offline_since.is_none() {
+            state.offline_since = Some(Utc::now());
+        } else if !offline && !state.network_unreachable {
+            state.offline_since = None;
+        }
+
+        info!("Manual offline mode {}", if offline { "enabled" } else { "disabled" });
+    }
+
+    /// Records a failed request; returns whether it switched BrewDeck offline
+    pub async fn record_failure(&self, error: &BrewDeckError) -> bool {
+        if !Self::indicates_offline(error) {
+            return false;
+        }
+
+        let mut state = self.state.write().await;
+        state.last_error = Some(error.to_string());
+
+        if state.network_unreachable {
+            return false;
+        }
+
+        warn!("Network unreachable, switching to offline mode: {}", error);
+        state.network_unreachable = true;
+        state.offline_since.get_or_insert_with(Utc::now);
+        true
+    }
+
+    /// Records a successful request; returns whether it brought BrewDeck back online
+    pub async fn record_success(&self) -> bool {
+        let mut state = self.state.write().await;
+
+        if !state.network_unreachable {
+            return false;
+        }
+
+        info!("Network reachable again");
+        state.network_unreachable = false;
+        state.last_error = None;
+        if !state.manual {
+            state.offline_since = None;
+        }
+        true
+    }
+
+    pub async fn queue_refresh(&self, package_type: PackageType) {
+        self.state.write().await.pending_refreshes.insert(package_type);
+    }
+
+    pub async fn take_pending_refreshes(&self) -> Vec<PackageType> {
+        std::mem::take(&mut self.state.write().await.pending_refreshes)
+            .into_iter()
+            .collect()
+    }
+
+    pub async fn status(&self, catalogs: Vec<CatalogFreshness>) -> OfflineStatus {
+        let state = self.state.read().await;
+
+        OfflineStatus {
+            offline: state.manual || state.network_unreachable,
+            manual: state.manual,
+            network_unreachable: state.network_unreachable,
+            offline_since: state.offline_since,
+            last_error: state.last_error.clone(),
+            pending_refreshes: state.pending_refreshes.iter().copied().collect(),
+            catalogs,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn server_errors_do_not_switch_to_offline() {
+        let monitor = ConnectivityMonitor::new();
+        let bad_gateway = BrewDeckError::HttpStatusError("formulae.brew.sh answered 502".to_string(), 502);
+
+        assert!(!monitor.record_failure(&bad_gateway).await);
+        assert!(!monitor.is_offline().await);
+        assert!(ConnectivityMonitor::can_serve_snapshot(&bad_gateway));
+    }
+
+    #[tokio::test]
+    async fn connection_failures_switch_to_offline() {
+        let monitor = ConnectivityMonitor::new();
+        let refused = BrewDeckError::NetworkError("Connection failed: connection refused".to_string());
+
+        assert!(monitor.record_failure(&refused).await);
+        assert!(monitor.is_offline().await);
+    }
+
+    #[test]
+    fn client_errors_are_not_served_from_a_snapshot() {
+        let not_found = BrewDeckError::HttpStatusError("formulae.brew.sh answered 404".to_string(), 404);
+
+        assert!(!ConnectivityMonitor::indicates_offline(&not_found));
+        assert!(!ConnectivityMonitor::can_serve_snapshot(&not_found));
+    }
+}
@@ -0,0 +1,359 @@
+use crate::error::BrewDeckError;
+use crate::services::brew_client::PackageType;
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use tracing::{debug, warn};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KegDiskUsage {
+    pub version: String,
+    pub path: String,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageDiskUsage {
+    pub name: String,
+    pub package_type: PackageType,
+    pub bytes: u64,
+    pub kegs: Vec<KegDiskUsage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiskUsageSummary {
+    pub total_bytes: u64,
+    pub formula_bytes: u64,
+    pub cask_bytes: u64,
+    /// Largest packages first
+    pub packages: Vec<PackageDiskUsage>,
+    pub computed_at: DateTime<Utc>,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct CachedSize {
+    modified: SystemTime,
+    bytes: u64,
+}
+
+/// Computes on-disk sizes of installed kegs by walking the Cellar and Caskroom.
+///
+/// Formula kegs live under `<prefix>/Cellar/<name>/<version>` and are sized per
+/// version. Casks are sized as a whole `<prefix>/Caskroom/<token>` directory;
+/// artifacts moved out of the Caskroom, such as `.app` bundles in
+/// `/Applications`, are not counted.
+#[derive(Default)]
+pub struct DiskUsageCalculator {
+    cache: Arc<DashMap<PathBuf, CachedSize>>,
+}
+
+impl DiskUsageCalculator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn package_usage(
+        &self,
+        prefix: &Path,
+        name: &str,
+        package_type: PackageType,
+    ) -> Result<Option<PackageDiskUsage>, BrewDeckError> {
+        let cache = Arc::clone(&self.cache);
+        let prefix = prefix.to_path_buf();
+        let name = name.to_string();
+
+        tokio::task::spawn_blocking(move || {
+            let root = package_root(&prefix, package_type);
+            package_usage_blocking(&cache, &root, &name, package_type)
+        })
+        .await
+        .map_err(|e| BrewDeckError::InternalError(format!("Disk usage task failed: {e}")))?
+    }
+
+    pub async fn summary(&self, prefix: &Path) -> Result<DiskUsageSummary, BrewDeckError> {
+        let cache = Arc::clone(&self.cache);
+        let prefix = prefix.to_path_buf();
+
+        tokio::task::spawn_blocking(move || summary_blocking(&cache, &prefix))
+            .await
+            .map_err(|e| BrewDeckError::InternalError(format!("Disk usage task failed: {e}")))?
+    }
+}
+
+fn package_root(prefix: &Path, package_type: PackageType) -> PathBuf {
+    match package_type {
+        PackageType::Formula => prefix.join("Cellar"),
+        PackageType::Cask => prefix.join("Caskroom"),
+    }
+}
+
+fn summary_blocking(
+    cache: &DashMap<PathBuf, CachedSize>,
+    prefix: &Path,
+) -> Result<DiskUsageSummary, BrewDeckError> {
+    let start_time = std::time::Instant::now();
+    let mut packages = Vec::new();
+
+    for package_type in [PackageType::Formula, PackageType::Cask] {
+        let root = package_root(prefix, package_type);
+        if !root.is_dir() {
+            debug!("Skipping missing {}", root.display());
+            continue;
+        }
+
+        for entry in std::fs::read_dir(&root)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with('.') {
+                continue;
+            }
+
+            match package_usage_blocking(cache, &root, &name, package_type) {
+                Ok(Some(usage)) => packages.push(usage),
+                Ok(None) => {}
+                Err(e) => warn!("Failed to compute disk usage for {}: {}", name, e),
+            }
+        }
+    }
+
+    packages.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.name.cmp(&b.name)));
+
+    let formula_bytes = packages
+        .iter()
+        .filter(|p| p.package_type == PackageType::Formula)
+        .map(|p| p.bytes)
+        .sum();
+    let cask_bytes = packages
+        .iter()
+        .filter(|p| p.package_type == PackageType::Cask)
+        .map(|p| p.bytes)
+        .sum();
+
+    Ok(DiskUsageSummary {
+        total_bytes: formula_bytes + cask_bytes,
+        formula_bytes,
+        cask_bytes,
+        packages,
+        computed_at: Utc::now(),
+        duration_ms: start_time.elapsed().as_millis() as u64,
+    })
+}
+
+fn package_usage_blocking(
+    cache: &DashMap<PathBuf, CachedSize>,
+    root: &Path,
+    name: &str,
+    package_type: PackageType,
+) -> Result<Option<PackageDiskUsage>, BrewDeckError> {
+    let package_dir = root.join(name);
+    if !package_dir.is_dir() {
+        return Ok(None);
+    }
+
+    let mut kegs = Vec::new();
+
+    match package_type {
+        PackageType::Formula => {
+            for entry in std::fs::read_dir(&package_dir)? {
+                let entry = entry?;
+                let version = entry.file_name().to_string_lossy().to_string();
+                let keg_path = entry.path();
+
+                if version.starts_with('.') || !keg_path.is_dir() {
+                    continue;
+                }
+
+                kegs.push(KegDiskUsage {
+                    bytes: cached_keg_size(cache, &keg_path)?,
+                    path: keg_path.display().to_string(),
+                    version,
+                });
+            }
+        }
+        PackageType::Cask => {
+            kegs.push(KegDiskUsage {
+                version: String::new(),
+                path: package_dir.display().to_string(),
+                bytes: cached_keg_size(cache, &package_dir)?,
+            });
+        }
+    }
+
+    Ok(Some(PackageDiskUsage {
+        name: name.to_string(),
+        package_type,
+        bytes: kegs.iter().map(|keg| keg.bytes).sum(),
+        kegs,
+    }))
+}
+
+/// Kegs are replaced rather than modified in place, so the directory mtime is
+/// enough to tell whether a cached size is still valid.
+fn cached_keg_size(cache: &DashMap<PathBuf, CachedSize>, keg_path: &Path) -> Result<u64, BrewDeckError> {
+    let modified = std::fs::symlink_metadata(keg_path)?.modified()?;
+
+    if let Some(cached) = cache.get(keg_path) {
+        if cached.modified == modified {
+            return Ok(cached.bytes);
+        }
+    }
+
+    let mut seen_inodes = HashSet::new();
+    let bytes = walk_size(keg_path, &mut seen_inodes)?;
+    cache.insert(keg_path.to_path_buf(), CachedSize { modified, bytes });
+
+    Ok(bytes)
+}
+
+/// Sums allocated sizes without following symlinks, counting each hard-linked
+/// file once.
+fn walk_size(path: &Path, seen_inodes: &mut HashSet<(u64, u64)>) -> std::io::Result<u64> {
+    let metadata = std::fs::symlink_metadata(path)?;
+
+    if metadata.file_type().is_symlink() {
+        return Ok(allocated_size(&metadata));
+    }
+
+    if metadata.is_dir() {
+        let mut total = allocated_size(&metadata);
+        for entry in std::fs::read_dir(path)? {
+            match walk_size(&entry?.path(), seen_inodes) {
+                Ok(bytes) => total += bytes,
+                // Files can disappear mid-walk while brew is cleaning up
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        return Ok(total);
+    }
+
+    if !first_link(&metadata, seen_inodes) {
+        return Ok(0);
+    }
+
+    Ok(allocated_size(&metadata))
+}
+
+#[cfg(unix)]
+fn first_link(metadata: &std::fs::Metadata, seen_inodes: &mut HashSet<(u64, u64)>) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    metadata.nlink() <= 1 || seen_inodes.insert((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn first_link(_metadata: &std::fs::Metadata, _seen_inodes: &mut HashSet<(u64, u64)>) -> bool {
+    true
+}
+
+#[cfg(unix)]
+fn allocated_size(metadata: &std::fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+
+    metadata.blocks() * 512
+}
+
+#[cfg(not(unix))]
+fn allocated_size(metadata: &std::fs::Metadata) -> u64 {
+    metadata.len()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::time::Duration;
+
+    const FILE_BYTES: usize = 64 * 1024;
+
+    /// A throwaway Cellar with one keg
+    struct FixtureKeg {
+        root: PathBuf,
+        keg: PathBuf,
+    }
+
+    impl FixtureKeg {
+        fn new(name: &str) -> Self {
+            let root = std::env::temp_dir().join(format!("brewdeck-{name}-{}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&root);
+
+            let keg = root.join("Cellar/wget/1.24.5");
+            std::fs::create_dir_all(keg.join("bin")).unwrap();
+            std::fs::write(keg.join("bin/wget"), vec![1u8; FILE_BYTES]).unwrap();
+
+            Self { root, keg }
+        }
+
+        fn size(&self) -> u64 {
+            walk_size(&self.keg, &mut HashSet::new()).unwrap()
+        }
+    }
+
+    impl Drop for FixtureKeg {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.root);
+        }
+    }
+
+    #[test]
+    fn counts_hard_linked_files_once() {
+        let fixture = FixtureKeg::new("disk-usage-hard-link");
+        let before = fixture.size();
+
+        std::fs::hard_link(fixture.keg.join("bin/wget"), fixture.keg.join("bin/wget-link")).unwrap();
+
+        assert!(before >= FILE_BYTES as u64);
+        assert_eq!(fixture.size(), before);
+    }
+
+    #[test]
+    fn does_not_follow_symlinks() {
+        let fixture = FixtureKeg::new("disk-usage-symlink");
+        let large = fixture.root.join("large.bin");
+        std::fs::write(&large, vec![1u8; 16 * FILE_BYTES]).unwrap();
+        let before = fixture.size();
+
+        std::os::unix::fs::symlink(&large, fixture.keg.join("bin/large")).unwrap();
+        std::os::unix::fs::symlink(&fixture.root, fixture.keg.join("loop")).unwrap();
+
+        // Only the links themselves, a few bytes each, are added
+        assert!(fixture.size() < before + FILE_BYTES as u64);
+    }
+
+    #[test]
+    fn reuses_cached_size_until_keg_mtime_changes() {
+        let fixture = FixtureKeg::new("disk-usage-cache");
+        let cache = DashMap::new();
+        let first = cached_keg_size(&cache, &fixture.keg).unwrap();
+
+        // A change below the keg root leaves its mtime alone, so the cached
+        // size is returned
+        std::fs::write(fixture.keg.join("bin/wget2"), vec![1u8; FILE_BYTES]).unwrap();
+        assert_eq!(cached_keg_size(&cache, &fixture.keg).unwrap(), first);
+
+        let touched = SystemTime::now() + Duration::from_secs(60);
+        File::open(&fixture.keg).unwrap().set_modified(touched).unwrap();
+        assert!(cached_keg_size(&cache, &fixture.keg).unwrap() >= first + FILE_BYTES as u64);
+    }
+
+    #[test]
+    fn sizes_each_formula_version() {
+        let fixture = FixtureKeg::new("disk-usage-versions");
+        std::fs::create_dir_all(fixture.root.join("Cellar/wget/1.21.4")).unwrap();
+        std::fs::create_dir_all(fixture.root.join("Cellar/wget/.metadata")).unwrap();
+
+        let usage = package_usage_blocking(&DashMap::new(), &fixture.root.join("Cellar"), "wget", PackageType::Formula)
+            .unwrap()
+            .unwrap();
+
+        let mut versions: Vec<&str> = usage.kegs.iter().map(|keg| keg.version.as_str()).collect();
+        versions.sort();
+        assert_eq!(versions, vec!["1.21.4", "1.24.5"]);
+        assert_eq!(usage.bytes, usage.kegs.iter().map(|keg| keg.bytes).sum::<u64>());
+    }
+}
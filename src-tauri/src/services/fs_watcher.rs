@@ -0,0 +1,290 @@
+use crate::error::BrewDeckError;
+use crate::services::brew_client::PackageType;
+use crate::services::CacheManager;
+use chrono::{DateTime, Utc};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+/// Packages touched outside BrewDeck, e.g. by `brew install` in a terminal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageChangeEvent {
+    pub formulae: Vec<String>,
+    pub casks: Vec<String>,
+    pub taps_changed: bool,
+    pub invalidated_entries: usize,
+    pub detected_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WatchedRoot {
+    Cellar,
+    Opt,
+    Caskroom,
+    Taps,
+}
+
+/// Watches the Cellar, Caskroom, `opt` and tap directories of a prefix and
+/// invalidates the affected cache entries once changes settle.
+pub struct PrefixWatcher {
+    // Dropping the watcher stops the notifications, which ends the debounce task
+    _watcher: RecommendedWatcher,
+}
+
+impl PrefixWatcher {
+    pub fn start<F>(
+        prefix: &Path,
+        cache: Arc<CacheManager>,
+        debounce: Duration,
+        on_change: F,
+    ) -> Result<Self, BrewDeckError>
+    where
+        F: Fn(PackageChangeEvent) + Send + Sync + 'static,
+    {
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        let mut watcher = notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+            match result {
+                Ok(event) if !event.kind.is_access() => {
+                    let _ = sender.send(event.paths);
+                }
+                Ok(_) => {}
+                Err(e) => warn!("File watcher error: {}", e),
+            }
+        })
+        .map_err(|e| BrewDeckError::InternalError(format!("Failed to create file watcher: {e}")))?;
+
+        // The Cellar is watched shallowly since it can hold hundreds of thousands
+        // of directories; upgrades still show up through the `opt` links.
+        let candidates = [
+            (prefix.join("Cellar"), WatchedRoot::Cellar, RecursiveMode::NonRecursive),
+            (prefix.join("opt"), WatchedRoot::Opt, RecursiveMode::NonRecursive),
+            (prefix.join("Caskroom"), WatchedRoot::Caskroom, RecursiveMode::Recursive),
+            (prefix.join("Library/Taps"), WatchedRoot::Taps, RecursiveMode::Recursive),
+            (prefix.join("Homebrew/Library/Taps"), WatchedRoot::Taps, RecursiveMode::Recursive),
+        ];
+
+        let mut roots = Vec::new();
+        for (path, root, mode) in candidates {
+            if !path.is_dir() {
+                continue;
+            }
+
+            match watcher.watch(&path, mode) {
+                Ok(()) => {
+                    debug!("Watching {} for external changes", path.display());
+                    roots.push((path, root));
+                }
+                Err(e) => warn!("Failed to watch {}: {}", path.display(), e),
+            }
+        }
+
+        if roots.is_empty() {
+            return Err(BrewDeckError::HomebrewNotFound(format!(
+                "Nothing to watch under {}",
+                prefix.display()
+            )));
+        }
+
+        info!("Watching {} Homebrew directories for external changes", roots.len());
+
+        tokio::spawn(Self::debounce_loop(receiver, roots, cache, debounce, on_change));
+
+        Ok(Self { _watcher: watcher })
+    }
+
+    async fn debounce_loop<F>(
+        mut receiver: mpsc::UnboundedReceiver<Vec<PathBuf>>,
+        roots: Vec<(PathBuf, WatchedRoot)>,
+        cache: Arc<CacheManager>,
+        debounce: Duration,
+        on_change: F,
+    ) where
+        F: Fn(PackageChangeEvent) + Send + Sync + 'static,
+    {
+        while let Some(paths) = receiver.recv().await {
+            let mut batch = paths;
+
+            // Keep collecting until brew has been quiet for the debounce window
+            loop {
+                match tokio::time::timeout(debounce, receiver.recv()).await {
+                    Ok(Some(paths)) => batch.extend(paths),
+                    Ok(None) => return,
+                    Err(_) => break,
+                }
+            }
+
+            let changes = ExternalChanges::classify(&roots, &batch);
+            if changes.is_empty() {
+                continue;
+            }
+
+            let tags = changes.invalidation_tags();
+            let invalidated_entries = cache.invalidate_by_tags(&tags).await;
+            info!(
+                "External Homebrew changes detected ({} formulae, {} casks, taps changed: {}); invalidated {} cache entries",
+                changes.formulae.len(),
+                changes.casks.len(),
+                changes.taps_changed,
+                invalidated_entries
+            );
+
+            on_change(PackageChangeEvent {
+                formulae: changes.formulae.into_iter().collect(),
+                casks: changes.casks.into_iter().collect(),
+                taps_changed: changes.taps_changed,
+                invalidated_entries,
+                detected_at: Utc::now(),
+            });
+        }
+    }
+}
+
+/// What one debounced batch of paths changed
+#[derive(Debug, Default)]
+struct ExternalChanges {
+    formulae: BTreeSet<String>,
+    casks: BTreeSet<String>,
+    taps_changed: bool,
+}
+
+impl ExternalChanges {
+    fn classify(roots: &[(PathBuf, WatchedRoot)], paths: &[PathBuf]) -> Self {
+        let mut changes = Self::default();
+
+        for path in paths {
+            match classify_path(roots, path) {
+                Some((WatchedRoot::Cellar | WatchedRoot::Opt, Some(name))) => {
+                    changes.formulae.insert(name);
+                }
+                Some((WatchedRoot::Caskroom, Some(token))) => {
+                    changes.casks.insert(token);
+                }
+                Some((WatchedRoot::Taps, _)) => changes.taps_changed = true,
+                _ => {}
+            }
+        }
+
+        changes
+    }
+
+    fn is_empty(&self) -> bool {
+        self.formulae.is_empty() && self.casks.is_empty() && !self.taps_changed
+    }
+
+    /// Cache tags to invalidate: every search, the listing of each changed
+    /// type and the details of each changed package; a tap change can touch
+    /// any package
+    fn invalidation_tags(&self) -> Vec<String> {
+        let mut tags = vec!["search".to_string()];
+        if self.taps_changed {
+            tags.push("packages".to_string());
+            tags.push("package_details".to_string());
+        }
+        for (package_type, names) in [(PackageType::Formula, &self.formulae), (PackageType::Cask, &self.casks)] {
+            if names.is_empty() {
+                continue;
+            }
+            tags.push(format!("packages_{package_type}"));
+            tags.extend(names.iter().map(|name| format!("package_{package_type}_{name}")));
+        }
+
+        tags
+    }
+}
+
+/// Maps a changed path to the watched root it belongs to and the package
+/// directory directly beneath it, e.g. `Cellar/wget/1.24.5` -> `wget`.
+fn classify_path(roots: &[(PathBuf, WatchedRoot)], path: &Path) -> Option<(WatchedRoot, Option<String>)> {
+    let (relative, root) = roots
+        .iter()
+        .find_map(|(root_path, root)| path.strip_prefix(root_path).ok().map(|relative| (relative, *root)))?;
+
+    let name = relative
+        .components()
+        .next()
+        .map(|component| component.as_os_str().to_string_lossy().to_string())
+        .filter(|name| !name.starts_with('.'));
+
+    Some((root, name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roots() -> Vec<(PathBuf, WatchedRoot)> {
+        let prefix = Path::new("/opt/homebrew");
+        vec![
+            (prefix.join("Cellar"), WatchedRoot::Cellar),
+            (prefix.join("opt"), WatchedRoot::Opt),
+            (prefix.join("Caskroom"), WatchedRoot::Caskroom),
+            (prefix.join("Library/Taps"), WatchedRoot::Taps),
+        ]
+    }
+
+    fn classify(path: &str) -> Option<(WatchedRoot, Option<String>)> {
+        classify_path(&roots(), Path::new(path))
+    }
+
+    #[test]
+    fn maps_paths_to_root_and_package() {
+        assert_eq!(classify("/opt/homebrew/Cellar/wget/1.24.5"), Some((WatchedRoot::Cellar, Some("wget".to_string()))));
+        assert_eq!(classify("/opt/homebrew/opt/wget"), Some((WatchedRoot::Opt, Some("wget".to_string()))));
+        assert_eq!(
+            classify("/opt/homebrew/Caskroom/firefox/126.0/Firefox.app"),
+            Some((WatchedRoot::Caskroom, Some("firefox".to_string())))
+        );
+        assert_eq!(
+            classify("/opt/homebrew/Library/Taps/homebrew/homebrew-cask"),
+            Some((WatchedRoot::Taps, Some("homebrew".to_string())))
+        );
+        assert_eq!(classify("/opt/homebrew/var/log/wget.log"), None);
+    }
+
+    #[test]
+    fn ignores_dot_files() {
+        assert_eq!(classify("/opt/homebrew/Cellar/.DS_Store"), Some((WatchedRoot::Cellar, None)));
+        assert_eq!(classify("/opt/homebrew/Caskroom/.metadata/firefox"), Some((WatchedRoot::Caskroom, None)));
+
+        let changes = ExternalChanges::classify(
+            &roots(),
+            &[PathBuf::from("/opt/homebrew/Cellar/.DS_Store"), PathBuf::from("/opt/homebrew/opt/.keepme")],
+        );
+        assert!(changes.is_empty());
+    }
+
+    #[test]
+    fn invalidates_changed_packages() {
+        let changes = ExternalChanges::classify(
+            &roots(),
+            &[
+                PathBuf::from("/opt/homebrew/Cellar/wget/1.24.5"),
+                PathBuf::from("/opt/homebrew/opt/wget"),
+                PathBuf::from("/opt/homebrew/Caskroom/firefox/126.0"),
+            ],
+        );
+
+        assert!(!changes.taps_changed);
+        assert_eq!(
+            changes.invalidation_tags(),
+            vec!["search", "packages_formula", "package_formula_wget", "packages_cask", "package_cask_firefox"]
+        );
+    }
+
+    #[test]
+    fn tap_changes_invalidate_all_listings_and_details() {
+        let changes = ExternalChanges::classify(
+            &roots(),
+            &[PathBuf::from("/opt/homebrew/Library/Taps/homebrew/homebrew-core/Formula/w/wget.rb")],
+        );
+
+        assert!(changes.taps_changed);
+        assert_eq!(changes.invalidation_tags(), vec!["search", "packages", "package_details"]);
+    }
+}
@@ -0,0 +1,440 @@
+use crate::error::BrewDeckError;
+use crate::services::brew_client::PackageType;
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstalledPackage {
+    pub name: String,
+    pub package_type: PackageType,
+    /// Every installed version, oldest install first
+    pub versions: Vec<String>,
+    /// The version `opt/<name>` points at, or the most recently installed one
+    pub current_version: Option<String>,
+    pub installed_time: Option<DateTime<Utc>>,
+    pub installed_on_request: bool,
+    pub installed_as_dependency: bool,
+    pub poured_from_bottle: bool,
+    pub runtime_dependencies: Vec<String>,
+    pub tap: Option<String>,
+    /// Set when the state came from `brew outdated` because the Cellar could not be read
+    pub reported_outdated: Option<bool>,
+}
+
+impl InstalledPackage {
+    /// Minimal entry for packages only known by name, e.g. from `brew list`
+    pub fn from_name(name: &str, package_type: PackageType) -> Self {
+        Self {
+            name: name.to_string(),
+            package_type,
+            versions: Vec::new(),
+            current_version: None,
+            installed_time: None,
+            installed_on_request: true,
+            installed_as_dependency: false,
+            poured_from_bottle: false,
+            runtime_dependencies: Vec::new(),
+            tap: None,
+            reported_outdated: None,
+        }
+    }
+
+    /// Whether none of the installed versions matches `latest_version`.
+    ///
+    /// Installed formula versions carry a `_<revision>` suffix that `brew info`
+    /// does not print, so a match ignoring the revision also counts. Casks with
+    /// `auto_updates true` update themselves, so like `brew outdated --cask`
+    /// they only count as outdated when they are upgraded greedily.
+    pub fn is_outdated(&self, latest_version: &str, auto_updates: bool, greedy: bool) -> bool {
+        if let Some(outdated) = self.reported_outdated {
+            return outdated;
+        }
+
+        if auto_updates && !greedy {
+            return false;
+        }
+
+        if self.versions.is_empty() || latest_version == "latest" || latest_version == "unknown" {
+            return false;
+        }
+
+        !self.versions.iter().any(|version| {
+            version == latest_version
+                || version.starts_with("HEAD")
+                || version.rsplit_once('_').map(|(base, _)| base) == Some(latest_version)
+        })
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct InstallReceipt {
+    installed_on_request: bool,
+    installed_as_dependency: bool,
+    poured_from_bottle: bool,
+    time: Option<i64>,
+    runtime_dependencies: serde_json::Value,
+    source: Option<ReceiptSource>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ReceiptSource {
+    tap: Option<String>,
+}
+
+/// Reads installed packages straight from the Cellar and Caskroom instead of
+/// spawning `brew list`, using each keg's `INSTALL_RECEIPT.json`.
+pub struct InstalledStateReader {
+    prefix: PathBuf,
+}
+
+impl InstalledStateReader {
+    pub fn new(prefix: impl Into<PathBuf>) -> Self {
+        Self {
+            prefix: prefix.into(),
+        }
+    }
+
+    pub async fn scan(&self, package_type: PackageType) -> Result<HashMap<String, InstalledPackage>, BrewDeckError> {
+        let prefix = self.prefix.clone();
+
+        tokio::task::spawn_blocking(move || InstalledStateReader::new(prefix).read(package_type))
+            .await
+            .map_err(|e| BrewDeckError::InternalError(format!("Installed state task failed: {e}")))?
+    }
+
+    pub fn read(&self, package_type: PackageType) -> Result<HashMap<String, InstalledPackage>, BrewDeckError> {
+        let start_time = std::time::Instant::now();
+        let root = match package_type {
+            PackageType::Formula => self.prefix.join("Cellar"),
+            PackageType::Cask => self.prefix.join("Caskroom"),
+        };
+
+        if !root.is_dir() {
+            // A prefix without casks is normal; one without a Cellar is not
+            return match package_type {
+                PackageType::Formula => Err(BrewDeckError::HomebrewNotFound(format!(
+                    "No Cellar found at {}",
+                    root.display()
+                ))),
+                PackageType::Cask => Ok(HashMap::new()),
+            };
+        }
+
+        let mut packages = HashMap::new();
+
+        for entry in std::fs::read_dir(&root)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with('.') || !entry.path().is_dir() {
+                continue;
+            }
+
+            let package = match package_type {
+                PackageType::Formula => self.read_formula(&entry.path(), &name),
+                PackageType::Cask => read_cask(&entry.path(), &name),
+            };
+
+            match package {
+                Ok(Some(package)) => {
+                    packages.insert(name, package);
+                }
+                Ok(None) => {}
+                Err(e) => warn!("Failed to read installed state for {}: {}", name, e),
+            }
+        }
+
+        debug!(
+            "Read {} installed {} packages from {} in {:?}",
+            packages.len(),
+            package_type,
+            root.display(),
+            start_time.elapsed()
+        );
+
+        Ok(packages)
+    }
+
+    fn read_formula(&self, rack: &Path, name: &str) -> Result<Option<InstalledPackage>, BrewDeckError> {
+        let mut kegs = Vec::new();
+
+        for entry in std::fs::read_dir(rack)? {
+            let entry = entry?;
+            let version = entry.file_name().to_string_lossy().to_string();
+            if version.starts_with('.') || !entry.path().is_dir() {
+                continue;
+            }
+
+            let receipt = read_receipt(&entry.path().join("INSTALL_RECEIPT.json"));
+            let time = receipt
+                .as_ref()
+                .and_then(|receipt| receipt.time)
+                .and_then(|time| Utc.timestamp_opt(time, 0).single())
+                .or_else(|| modified_time(&entry.path()));
+            kegs.push((version, receipt, time));
+        }
+
+        // An empty rack is left behind by interrupted uninstalls
+        if kegs.is_empty() {
+            return Ok(None);
+        }
+
+        kegs.sort_by(|a, b| a.2.cmp(&b.2).then_with(|| a.0.cmp(&b.0)));
+
+        let linked_version = std::fs::read_link(self.prefix.join("opt").join(name))
+            .ok()
+            .and_then(|target| target.file_name().map(|v| v.to_string_lossy().to_string()));
+        let current_index = linked_version
+            .as_ref()
+            .and_then(|linked| kegs.iter().position(|(version, _, _)| version == linked))
+            .unwrap_or(kegs.len() - 1);
+
+        let versions = kegs.iter().map(|(version, _, _)| version.clone()).collect();
+        let (current_version, receipt, installed_time) = kegs.swap_remove(current_index);
+        let receipt = receipt.unwrap_or_default();
+
+        Ok(Some(InstalledPackage {
+            name: name.to_string(),
+            package_type: PackageType::Formula,
+            versions,
+            current_version: Some(current_version),
+            installed_time,
+            installed_on_request: receipt.installed_on_request,
+            installed_as_dependency: receipt.installed_as_dependency,
+            poured_from_bottle: receipt.poured_from_bottle,
+            runtime_dependencies: dependency_names(&receipt.runtime_dependencies),
+            tap: receipt.source.and_then(|source| source.tap),
+            reported_outdated: None,
+        }))
+    }
+}
+
+fn read_cask(caskroom_dir: &Path, token: &str) -> Result<Option<InstalledPackage>, BrewDeckError> {
+    let mut versions = Vec::new();
+
+    for entry in std::fs::read_dir(caskroom_dir)? {
+        let entry = entry?;
+        let version = entry.file_name().to_string_lossy().to_string();
+        if !version.starts_with('.') && entry.path().is_dir() {
+            versions.push(version);
+        }
+    }
+
+    if versions.is_empty() {
+        return Ok(None);
+    }
+
+    let metadata_dir = caskroom_dir.join(".metadata");
+    let receipt = read_receipt(&metadata_dir.join("INSTALL_RECEIPT.json"));
+    let install_times: HashMap<String, DateTime<Utc>> = versions
+        .iter()
+        .filter_map(|version| {
+            cask_install_time(&metadata_dir.join(version))
+                .or_else(|| modified_time(&caskroom_dir.join(version)))
+                .map(|time| (version.clone(), time))
+        })
+        .collect();
+
+    versions.sort_by(|a, b| install_times.get(a).cmp(&install_times.get(b)).then_with(|| a.cmp(b)));
+    let current_version = versions.last().cloned();
+
+    let installed_time = receipt
+        .as_ref()
+        .and_then(|receipt| receipt.time)
+        .and_then(|time| Utc.timestamp_opt(time, 0).single())
+        .or_else(|| current_version.as_ref().and_then(|v| install_times.get(v).copied()));
+
+    // Casks installed before brew wrote cask receipts were always explicit installs
+    let installed_on_request = receipt.as_ref().is_none_or(|receipt| receipt.installed_on_request);
+    let receipt = receipt.unwrap_or_default();
+
+    Ok(Some(InstalledPackage {
+        name: token.to_string(),
+        package_type: PackageType::Cask,
+        versions,
+        current_version,
+        installed_time,
+        installed_on_request,
+        installed_as_dependency: receipt.installed_as_dependency,
+        poured_from_bottle: false,
+        runtime_dependencies: dependency_names(&receipt.runtime_dependencies),
+        tap: receipt.source.and_then(|source| source.tap),
+        reported_outdated: None,
+    }))
+}
+
+fn read_receipt(path: &Path) -> Option<InstallReceipt> {
+    let contents = std::fs::read(path).ok()?;
+
+    match serde_json::from_slice(&contents) {
+        Ok(receipt) => Some(receipt),
+        Err(e) => {
+            warn!("Failed to parse {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Formula receipts list `{"full_name": ...}` objects, cask receipts group
+/// names by kind (`{"formula": [...], "cask": [...]}`).
+fn dependency_names(value: &serde_json::Value) -> Vec<String> {
+    match value {
+        serde_json::Value::Array(items) => items
+            .iter()
+            .filter_map(|item| match item {
+                serde_json::Value::String(name) => Some(name.clone()),
+                other => other["full_name"].as_str().map(|name| name.to_string()),
+            })
+            .collect(),
+        serde_json::Value::Object(groups) => groups
+            .values()
+            .flat_map(dependency_names)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Casks record each install under `.metadata/<version>/<timestamp>/`,
+/// e.g. `20240115123045.123`.
+fn cask_install_time(version_metadata_dir: &Path) -> Option<DateTime<Utc>> {
+    std::fs::read_dir(version_metadata_dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            NaiveDateTime::parse_from_str(&name, "%Y%m%d%H%M%S%.3f")
+                .ok()
+                .map(|time| Utc.from_utc_datetime(&time))
+        })
+        .max()
+}
+
+fn modified_time(path: &Path) -> Option<DateTime<Utc>> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .map(DateTime::<Utc>::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A throwaway Homebrew prefix with one formula and one cask
+    struct FixturePrefix(PathBuf);
+
+    impl FixturePrefix {
+        fn new(name: &str) -> Self {
+            let root = std::env::temp_dir().join(format!("brewdeck-{name}-{}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&root);
+
+            let keg = root.join("Cellar/wget/1.24.5_1");
+            std::fs::create_dir_all(&keg).unwrap();
+            std::fs::write(
+                keg.join("INSTALL_RECEIPT.json"),
+                r#"{
+                    "installed_on_request": true,
+                    "installed_as_dependency": false,
+                    "poured_from_bottle": true,
+                    "time": 1717171717,
+                    "runtime_dependencies": [
+                        {"full_name": "libidn2", "version": "2.3.7"},
+                        {"full_name": "openssl@3", "version": "3.3.1"}
+                    ],
+                    "source": {"tap": "homebrew/core"}
+                }"#,
+            )
+            .unwrap();
+            std::fs::create_dir_all(root.join("Cellar/.keepme")).unwrap();
+
+            let caskroom = root.join("Caskroom/firefox");
+            std::fs::create_dir_all(caskroom.join("126.0")).unwrap();
+            std::fs::create_dir_all(caskroom.join(".metadata/126.0/20240115123045.123/Casks")).unwrap();
+
+            Self(root)
+        }
+    }
+
+    impl Drop for FixturePrefix {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn reads_kegs_and_receipts() {
+        let prefix = FixturePrefix::new("cellar");
+        let installed = InstalledStateReader::new(&prefix.0).read(PackageType::Formula).unwrap();
+
+        assert_eq!(installed.len(), 1);
+        let wget = &installed["wget"];
+        assert_eq!(wget.versions, ["1.24.5_1"]);
+        assert_eq!(wget.current_version.as_deref(), Some("1.24.5_1"));
+        assert_eq!(wget.installed_time, Utc.timestamp_opt(1717171717, 0).single());
+        assert!(wget.installed_on_request);
+        assert!(!wget.installed_as_dependency);
+        assert!(wget.poured_from_bottle);
+        assert_eq!(wget.runtime_dependencies, ["libidn2", "openssl@3"]);
+        assert_eq!(wget.tap.as_deref(), Some("homebrew/core"));
+    }
+
+    #[test]
+    fn reads_caskroom() {
+        let prefix = FixturePrefix::new("caskroom");
+        let installed = InstalledStateReader::new(&prefix.0).read(PackageType::Cask).unwrap();
+
+        let firefox = &installed["firefox"];
+        assert_eq!(firefox.versions, ["126.0"]);
+        assert_eq!(firefox.current_version.as_deref(), Some("126.0"));
+        assert_eq!(
+            firefox.installed_time,
+            Some(Utc.with_ymd_and_hms(2024, 1, 15, 12, 30, 45).unwrap() + chrono::Duration::milliseconds(123))
+        );
+        // No cask receipt means an install from before brew wrote them
+        assert!(firefox.installed_on_request);
+    }
+
+    #[test]
+    fn missing_prefix() {
+        let prefix = FixturePrefix::new("empty");
+        let reader = InstalledStateReader::new(prefix.0.join("missing"));
+
+        assert!(reader.read(PackageType::Formula).is_err());
+        assert!(reader.read(PackageType::Cask).unwrap().is_empty());
+    }
+
+    #[test]
+    fn outdated_ignores_revision() {
+        let prefix = FixturePrefix::new("outdated");
+        let installed = InstalledStateReader::new(&prefix.0).read(PackageType::Formula).unwrap();
+        let wget = &installed["wget"];
+
+        assert!(!wget.is_outdated("1.24.5_1", false, false));
+        assert!(!wget.is_outdated("1.24.5", false, false));
+        assert!(wget.is_outdated("1.25.0", false, false));
+    }
+
+    #[test]
+    fn auto_updating_casks_are_outdated_only_when_greedy() {
+        let prefix = FixturePrefix::new("auto-updates");
+        let installed = InstalledStateReader::new(&prefix.0).read(PackageType::Cask).unwrap();
+        let firefox = &installed["firefox"];
+
+        assert!(firefox.is_outdated("127.0", false, false));
+        assert!(!firefox.is_outdated("127.0", true, false));
+        assert!(firefox.is_outdated("127.0", true, true));
+        assert!(!firefox.is_outdated("latest", true, true));
+    }
+
+    #[test]
+    fn reported_state_wins() {
+        let mut package = InstalledPackage::from_name("firefox", PackageType::Cask);
+        package.reported_outdated = Some(true);
+
+        assert!(package.is_outdated("127.0", true, false));
+    }
+}
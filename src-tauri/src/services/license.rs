@@ -0,0 +1,575 @@
+use crate::error::BrewDeckError;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// Values brew uses where no SPDX expression applies; kept as single licenses
+const NON_SPDX_LICENSES: &[&str] = &["Public Domain", "Cannot Represent"];
+
+/// A parsed SPDX license expression. `WITH` binds tighter than `AND`, which
+/// binds tighter than `OR`; operators are case-insensitive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LicenseExpression {
+    License {
+        id: String,
+        exception: Option<String>,
+    },
+    And(Vec<LicenseExpression>),
+    Or(Vec<LicenseExpression>),
+}
+
+impl LicenseExpression {
+    pub fn parse(expression: &str) -> Result<Self, BrewDeckError> {
+        let expression = expression.trim();
+
+        if let Some(special) = NON_SPDX_LICENSES.iter().find(|special| special.eq_ignore_ascii_case(expression)) {
+            return Ok(LicenseExpression::License {
+                id: special.to_string(),
+                exception: None,
+            });
+        }
+
+        let tokens = tokenize(expression)?;
+        let mut parser = Parser {
+            expression,
+            tokens: &tokens,
+            position: 0,
+        };
+        let parsed = parser.parse_or()?;
+
+        if parser.next().is_some() {
+            return Err(parser.error("unexpected trailing input"));
+        }
+        Ok(parsed)
+    }
+
+    /// Every license id in the expression, in order of appearance
+    pub fn license_ids(&self) -> Vec<&str> {
+        match self {
+            LicenseExpression::License { id, .. } => vec![id.as_str()],
+            LicenseExpression::And(terms) | LicenseExpression::Or(terms) => {
+                terms.iter().flat_map(|term| term.license_ids()).collect()
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for LicenseExpression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let join = |f: &mut std::fmt::Formatter<'_>, terms: &[LicenseExpression], operator: &str| {
+            for (position, term) in terms.iter().enumerate() {
+                if position > 0 {
+                    write!(f, " {operator} ")?;
+                }
+                match term {
+                    LicenseExpression::License { .. } => write!(f, "{term}")?,
+                    _ => write!(f, "({term})")?,
+                }
+            }
+            Ok(())
+        };
+
+        match self {
+            LicenseExpression::License { id, exception: Some(exception) } => write!(f, "{id} WITH {exception}"),
+            LicenseExpression::License { id, exception: None } => write!(f, "{id}"),
+            LicenseExpression::And(terms) => join(f, terms, "AND"),
+            LicenseExpression::Or(terms) => join(f, terms, "OR"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    With,
+    Identifier(String),
+}
+
+fn tokenize(expression: &str) -> Result<Vec<(Token, usize)>, BrewDeckError> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push((Token::Open, start));
+            }
+            ')' => {
+                chars.next();
+                tokens.push((Token::Close, start));
+            }
+            c if is_identifier_char(c) => {
+                let mut word = String::new();
+                while let Some(&(_, c)) = chars.peek().filter(|(_, c)| is_identifier_char(*c)) {
+                    word.push(c);
+                    chars.next();
+                }
+
+                let token = match word.to_ascii_uppercase().as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "WITH" => Token::With,
+                    _ => Token::Identifier(word),
+                };
+                tokens.push((token, start));
+            }
+            c => {
+                return Err(BrewDeckError::ParsingError(format!(
+                    "Invalid license expression {expression:?}: unexpected {c:?} at {start}"
+                )));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '+' | ':' | '_')
+}
+
+struct Parser<'a> {
+    expression: &'a str,
+    tokens: &'a [(Token, usize)],
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn parse_or(&mut self) -> Result<LicenseExpression, BrewDeckError> {
+        let mut terms = vec![self.parse_and()?];
+        while self.eat(&Token::Or) {
+            terms.push(self.parse_and()?);
+        }
+
+        Ok(if terms.len() == 1 { terms.remove(0) } else { LicenseExpression::Or(terms) })
+    }
+
+    fn parse_and(&mut self) -> Result<LicenseExpression, BrewDeckError> {
+        let mut terms = vec![self.parse_with()?];
+        while self.eat(&Token::And) {
+            terms.push(self.parse_with()?);
+        }
+
+        Ok(if terms.len() == 1 { terms.remove(0) } else { LicenseExpression::And(terms) })
+    }
+
+    fn parse_with(&mut self) -> Result<LicenseExpression, BrewDeckError> {
+        match self.next() {
+            Some(Token::Open) => {
+                let inner = self.parse_or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(inner),
+                    _ => Err(self.error("expected ')'")),
+                }
+            }
+            Some(Token::Identifier(id)) => {
+                let id = id.clone();
+                let exception = if self.eat(&Token::With) {
+                    match self.next() {
+                        Some(Token::Identifier(exception)) => Some(exception.clone()),
+                        _ => return Err(self.error("expected a license exception after WITH")),
+                    }
+                } else {
+                    None
+                };
+                Ok(LicenseExpression::License { id, exception })
+            }
+            _ => Err(self.error("expected a license or '('")),
+        }
+    }
+
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.position).map(|(token, _)| token);
+        self.position += 1;
+        token
+    }
+
+    fn eat(&mut self, expected: &Token) -> bool {
+        match self.tokens.get(self.position) {
+            Some((token, _)) if token == expected => {
+                self.position += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Reports `message` at the token just read, or at the end of input
+    fn error(&self, message: &str) -> BrewDeckError {
+        let offset = self
+            .tokens
+            .get(self.position.saturating_sub(1))
+            .map_or(self.expression.len(), |(_, offset)| *offset);
+
+        BrewDeckError::ParsingError(format!(
+            "Invalid license expression {:?}: {} at {}",
+            self.expression, message, offset
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LicensePolicyMode {
+    /// Only the report flags violations
+    Off,
+    /// Installing a package with a violating license raises a warning
+    #[default]
+    Warn,
+    /// Installing a package with a violating license is refused
+    Block,
+}
+
+impl LicensePolicyMode {
+    /// Whether installs report license violations as warnings
+    pub fn warns(self) -> bool {
+        self != LicensePolicyMode::Off
+    }
+
+    /// Whether installs with license violations are refused
+    pub fn blocks(self) -> bool {
+        self == LicensePolicyMode::Block
+    }
+}
+
+/// Which licenses may be installed. License ids match case-insensitively;
+/// an entry can also name a license with its exception, e.g.
+/// `GPL-2.0-only WITH Classpath-exception-2.0`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LicensePolicy {
+    pub mode: LicensePolicyMode,
+    /// When not empty, only these licenses are acceptable
+    pub allow: Vec<String>,
+    /// Never acceptable, even when also allowed
+    pub deny: Vec<String>,
+    /// Treat packages without a (parseable) license as violations
+    pub flag_unknown: bool,
+}
+
+impl LicensePolicy {
+    /// Where the policy is kept unless configured otherwise
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("BrewDeck").join("license-policy.json"))
+    }
+
+    /// Reads the policy file; a missing file means the default policy
+    pub fn load(path: &Path) -> Result<Self, BrewDeckError> {
+        match std::fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| {
+                BrewDeckError::InvalidConfiguration(format!("Invalid license policy {}: {}", path.display(), e))
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), BrewDeckError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let temp_path = path.with_extension("json.tmp");
+        std::fs::write(&temp_path, serde_json::to_vec_pretty(self)?)?;
+        std::fs::rename(&temp_path, path)?;
+
+        info!("Saved license policy to {}", path.display());
+        Ok(())
+    }
+
+    /// Checks an expression against the policy. `AND` needs every license to
+    /// be acceptable, `OR` any one of them. Returns the offending licenses;
+    /// empty means compliant.
+    pub fn violations(&self, expression: &LicenseExpression) -> Vec<String> {
+        match expression {
+            LicenseExpression::License { id, exception } => {
+                let with_exception = exception.as_ref().map(|exception| format!("{id} WITH {exception}"));
+                let listed = |list: &[String]| {
+                    list.iter().any(|entry| {
+                        entry.eq_ignore_ascii_case(id)
+                            || with_exception.as_ref().is_some_and(|full| entry.eq_ignore_ascii_case(full))
+                    })
+                };
+
+                if listed(&self.deny) || (!self.allow.is_empty() && !listed(&self.allow)) {
+                    vec![expression.to_string()]
+                } else {
+                    Vec::new()
+                }
+            }
+            LicenseExpression::And(terms) => terms.iter().flat_map(|term| self.violations(term)).collect(),
+            LicenseExpression::Or(terms) => {
+                let per_term: Vec<Vec<String>> = terms.iter().map(|term| self.violations(term)).collect();
+                if per_term.iter().any(|violations| violations.is_empty()) {
+                    Vec::new()
+                } else {
+                    per_term.into_iter().flatten().collect()
+                }
+            }
+        }
+    }
+
+    /// Checks a package's raw license field
+    pub fn check(&self, license: Option<&str>) -> LicenseCheck {
+        let Some(license) = license.map(str::trim).filter(|license| !license.is_empty()) else {
+            return LicenseCheck {
+                status: LicenseStatus::Unknown,
+                license_ids: Vec::new(),
+                violations: Vec::new(),
+            };
+        };
+
+        match LicenseExpression::parse(license) {
+            Ok(expression) => {
+                let violations = self.violations(&expression);
+                LicenseCheck {
+                    status: if violations.is_empty() { LicenseStatus::Compliant } else { LicenseStatus::Violation },
+                    license_ids: expression.license_ids().into_iter().map(str::to_string).collect(),
+                    violations,
+                }
+            }
+            Err(_) => LicenseCheck {
+                status: LicenseStatus::Unparseable,
+                license_ids: vec![license.to_string()],
+                violations: Vec::new(),
+            },
+        }
+    }
+
+    /// Whether a check result goes against the policy
+    pub fn is_violation(&self, check: &LicenseCheck) -> bool {
+        match check.status {
+            LicenseStatus::Violation => true,
+            LicenseStatus::Unknown | LicenseStatus::Unparseable => self.flag_unknown,
+            LicenseStatus::Compliant => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LicenseStatus {
+    Compliant,
+    Violation,
+    /// The package declares no license
+    Unknown,
+    /// The license is not a valid SPDX expression
+    Unparseable,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LicenseCheck {
+    pub status: LicenseStatus,
+    pub license_ids: Vec<String>,
+    pub violations: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LicenseReportEntry {
+    pub name: String,
+    pub version: String,
+    pub license: Option<String>,
+    #[serde(flatten)]
+    pub check: LicenseCheck,
+    /// Against the policy, including unknown licenses when those are flagged
+    pub flagged: bool,
+}
+
+/// License inventory of the installed formulae
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LicenseReport {
+    pub generated_at: DateTime<Utc>,
+    pub policy: LicensePolicy,
+    pub packages: Vec<LicenseReportEntry>,
+    /// Installed packages per license id
+    pub license_counts: BTreeMap<String, usize>,
+    pub flagged_count: usize,
+    pub unknown_count: usize,
+}
+
+impl LicenseReport {
+    pub fn build(policy: LicensePolicy, packages: Vec<(String, String, Option<String>)>) -> Self {
+        let mut entries: Vec<LicenseReportEntry> = packages
+            .into_iter()
+            .map(|(name, version, license)| {
+                let check = policy.check(license.as_deref());
+                let flagged = policy.is_violation(&check);
+                LicenseReportEntry {
+                    name,
+                    version,
+                    license,
+                    check,
+                    flagged,
+                }
+            })
+            .collect();
+        entries.sort_by(|a, b| b.flagged.cmp(&a.flagged).then_with(|| a.name.cmp(&b.name)));
+
+        let mut license_counts = BTreeMap::new();
+        for entry in &entries {
+            for id in &entry.check.license_ids {
+                *license_counts.entry(id.clone()).or_insert(0) += 1;
+            }
+        }
+
+        Self {
+            generated_at: Utc::now(),
+            flagged_count: entries.iter().filter(|entry| entry.flagged).count(),
+            unknown_count: entries
+                .iter()
+                .filter(|entry| matches!(entry.check.status, LicenseStatus::Unknown | LicenseStatus::Unparseable))
+                .count(),
+            policy,
+            packages: entries,
+            license_counts,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn license(id: &str) -> LicenseExpression {
+        LicenseExpression::License {
+            id: id.to_string(),
+            exception: None,
+        }
+    }
+
+    fn policy(allow: &[&str], deny: &[&str]) -> LicensePolicy {
+        LicensePolicy {
+            allow: allow.iter().map(|id| id.to_string()).collect(),
+            deny: deny.iter().map(|id| id.to_string()).collect(),
+            ..LicensePolicy::default()
+        }
+    }
+
+    #[test]
+    fn with_binds_tighter_than_and_tighter_than_or() {
+        let parsed = LicenseExpression::parse("MIT or Apache-2.0 AND GPL-2.0-only WITH Classpath-exception-2.0").unwrap();
+
+        assert_eq!(
+            parsed,
+            LicenseExpression::Or(vec![
+                license("MIT"),
+                LicenseExpression::And(vec![
+                    license("Apache-2.0"),
+                    LicenseExpression::License {
+                        id: "GPL-2.0-only".to_string(),
+                        exception: Some("Classpath-exception-2.0".to_string()),
+                    },
+                ]),
+            ])
+        );
+        assert_eq!(parsed.license_ids(), vec!["MIT", "Apache-2.0", "GPL-2.0-only"]);
+    }
+
+    #[test]
+    fn parentheses_override_precedence() {
+        let parsed = LicenseExpression::parse("(MIT OR Apache-2.0) AND BSD-3-Clause").unwrap();
+
+        assert_eq!(
+            parsed,
+            LicenseExpression::And(vec![
+                LicenseExpression::Or(vec![license("MIT"), license("Apache-2.0")]),
+                license("BSD-3-Clause"),
+            ])
+        );
+        assert_eq!(parsed.to_string(), "(MIT OR Apache-2.0) AND BSD-3-Clause");
+        assert_eq!(LicenseExpression::parse("public domain").unwrap(), license("Public Domain"));
+    }
+
+    #[test]
+    fn rejects_malformed_expressions() {
+        for expression in ["MIT AND", "(MIT", "", "MIT WITH", "MIT)", "MIT / BSD"] {
+            assert!(
+                matches!(LicenseExpression::parse(expression), Err(BrewDeckError::ParsingError(_))),
+                "{expression:?} should not parse"
+            );
+        }
+
+        let check = LicensePolicy::default().check(Some("(MIT"));
+        assert_eq!(check.status, LicenseStatus::Unparseable);
+        assert_eq!(LicensePolicy::default().check(Some("  ")).status, LicenseStatus::Unknown);
+    }
+
+    #[test]
+    fn deny_beats_allow() {
+        let policy = policy(&["MIT", "GPL-3.0-only"], &["gpl-3.0-only"]);
+
+        assert!(policy.violations(&license("MIT")).is_empty());
+        assert_eq!(policy.violations(&license("GPL-3.0-only")), vec!["GPL-3.0-only"]);
+    }
+
+    #[test]
+    fn or_needs_any_and_needs_every_license() {
+        let policy = policy(&["MIT", "Apache-2.0"], &[]);
+
+        assert_eq!(policy.check(Some("MIT OR GPL-3.0-only")).status, LicenseStatus::Compliant);
+        assert_eq!(
+            policy.check(Some("GPL-2.0-only OR GPL-3.0-only")).violations,
+            vec!["GPL-2.0-only", "GPL-3.0-only"]
+        );
+        assert_eq!(policy.check(Some("MIT AND Apache-2.0")).status, LicenseStatus::Compliant);
+        assert_eq!(policy.check(Some("MIT AND GPL-3.0-only")).violations, vec!["GPL-3.0-only"]);
+    }
+
+    #[test]
+    fn matches_license_with_exception() {
+        let policy = policy(&["GPL-2.0-only WITH Classpath-exception-2.0"], &[]);
+
+        assert!(policy.check(Some("GPL-2.0-only WITH Classpath-exception-2.0")).violations.is_empty());
+        assert_eq!(policy.check(Some("GPL-2.0-only")).status, LicenseStatus::Violation);
+    }
+
+    #[test]
+    fn flags_unknown_licenses_only_when_asked() {
+        let mut policy = policy(&[], &["GPL-3.0-only"]);
+        let unknown = policy.check(None);
+        let unparseable = policy.check(Some("MIT AND"));
+
+        assert!(!policy.is_violation(&unknown));
+        assert!(!policy.is_violation(&unparseable));
+
+        policy.flag_unknown = true;
+        assert!(policy.is_violation(&unknown));
+        assert!(policy.is_violation(&unparseable));
+        assert!(!policy.is_violation(&policy.check(Some("MIT"))));
+    }
+
+    #[test]
+    fn mode_decides_warning_and_blocking() {
+        assert!(!LicensePolicyMode::Off.warns());
+        assert!(!LicensePolicyMode::Off.blocks());
+        assert!(LicensePolicyMode::Warn.warns());
+        assert!(!LicensePolicyMode::Warn.blocks());
+        assert!(LicensePolicyMode::Block.warns());
+        assert!(LicensePolicyMode::Block.blocks());
+
+        // The report flags violations whatever the mode
+        for mode in [LicensePolicyMode::Off, LicensePolicyMode::Warn, LicensePolicyMode::Block] {
+            let report = LicenseReport::build(
+                LicensePolicy { mode, ..policy(&[], &["GPL-3.0-only"]) },
+                vec![
+                    ("wget".to_string(), "1.24.5".to_string(), Some("GPL-3.0-or-later".to_string())),
+                    ("bash".to_string(), "5.2".to_string(), Some("GPL-3.0-only".to_string())),
+                    ("jq".to_string(), "1.7".to_string(), None),
+                ],
+            );
+
+            assert_eq!(report.flagged_count, 1);
+            assert_eq!(report.unknown_count, 1);
+            assert_eq!(report.packages[0].name, "bash");
+        }
+    }
+}
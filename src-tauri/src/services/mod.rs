@@ -2,7 +2,24 @@ pub mod package_service;
 pub mod cache_manager;
 pub mod brew_client;
 pub mod api_client;
+pub mod catalog_store;
+pub mod catalog_index;
+pub mod analytics;
+pub mod signature;
+pub mod connectivity;
+pub mod http_client;
+pub mod rate_limiter;
+pub mod platform;
+pub mod license;
+pub mod cask_requirements;
+pub mod search;
+pub mod search_index;
+pub mod package_query;
+pub mod query_language;
 pub mod prefetch_service;
+pub mod disk_usage;
+pub mod installed_state;
+pub mod fs_watcher;
 
 pub use package_service::PackageService;
 pub use cache_manager::CacheManager;
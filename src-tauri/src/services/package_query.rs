@@ -0,0 +1,514 @@
+use crate::error::BrewDeckError;
+use crate::services::brew_client::PackageType;
+use crate::services::connectivity::CatalogFreshness;
+use crate::services::license::LicenseExpression;
+use crate::services::package_service::BrewPackage;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+pub const MAX_PAGE_SIZE: usize = 500;
+
+/// Server-side filters; unset fields match everything
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PackageFilters {
+    pub installed: Option<bool>,
+    pub outdated: Option<bool>,
+    pub deprecated: Option<bool>,
+    /// e.g. `homebrew/core`, compared case-insensitively
+    pub tap: Option<String>,
+    /// An SPDX id the package's license expression must mention, e.g. `MIT`
+    pub license: Option<String>,
+    /// Minimum installs over the last 365 days
+    pub min_downloads: Option<u64>,
+}
+
+impl PackageFilters {
+    pub fn matches(&self, package: &BrewPackage) -> bool {
+        self.installed.is_none_or(|installed| package.installed == installed)
+            && self.outdated.is_none_or(|outdated| package.outdated == outdated)
+            && self.deprecated.is_none_or(|deprecated| package.deprecated == deprecated)
+            && self.tap.as_deref().is_none_or(|tap| {
+                package.tap.as_deref().is_some_and(|package_tap| package_tap.eq_ignore_ascii_case(tap))
+            })
+            && self.license.as_deref().is_none_or(|license| mentions_license(package, license))
+            && self.min_downloads.is_none_or(|minimum| package.analytics.downloads_365d >= minimum)
+    }
+}
+
+/// Whether `license` is one of the ids in the package's expression; an
+/// expression that does not parse is compared as a whole
+pub fn mentions_license(package: &BrewPackage, license: &str) -> bool {
+    let Some(expression) = package.license.as_deref() else {
+        return false;
+    };
+
+    match LicenseExpression::parse(expression) {
+        Ok(parsed) => parsed.license_ids().iter().any(|id| id.eq_ignore_ascii_case(license)),
+        Err(_) => expression.eq_ignore_ascii_case(license),
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PackageSortKey {
+    #[default]
+    Name,
+    /// Installs over the last 365 days
+    Popularity,
+    InstallDate,
+    Size,
+}
+
+impl PackageSortKey {
+    /// Names read A to Z; the others are most useful largest first
+    fn default_direction(self) -> SortDirection {
+        match self {
+            PackageSortKey::Name => SortDirection::Ascending,
+            _ => SortDirection::Descending,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortDirection {
+    Ascending,
+    Descending,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Projection {
+    /// What a list row shows
+    #[default]
+    Summary,
+    Full,
+}
+
+/// One page request over a package listing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PackageQuery {
+    pub filters: PackageFilters,
+    pub sort: PackageSortKey,
+    /// Defaults to ascending for names and descending otherwise
+    pub direction: Option<SortDirection>,
+    pub projection: Projection,
+    /// Ignored when a cursor is given
+    pub offset: usize,
+    /// `next_cursor` of the previous page
+    pub cursor: Option<String>,
+    pub page_size: usize,
+}
+
+impl Default for PackageQuery {
+    fn default() -> Self {
+        Self {
+            filters: PackageFilters::default(),
+            sort: PackageSortKey::default(),
+            direction: None,
+            projection: Projection::default(),
+            offset: 0,
+            cursor: None,
+            page_size: DEFAULT_PAGE_SIZE,
+        }
+    }
+}
+
+/// The fields a list row needs, without caveats, dependencies or warnings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageSummary {
+    pub name: String,
+    pub version: String,
+    pub description: String,
+    pub package_type: PackageType,
+    pub tap: Option<String>,
+    pub license: Option<String>,
+    pub installed: bool,
+    pub outdated: bool,
+    pub deprecated: bool,
+    pub downloads_365d: u64,
+    pub popularity: f64,
+    pub installed_time: Option<DateTime<Utc>>,
+    pub install_size: Option<u64>,
+    pub warning_count: usize,
+}
+
+impl From<&BrewPackage> for PackageSummary {
+    fn from(package: &BrewPackage) -> Self {
+        Self {
+            name: package.name.clone(),
+            version: package.version.clone(),
+            description: package.description.clone(),
+            package_type: package.package_type,
+            tap: package.tap.clone(),
+            license: package.license.clone(),
+            installed: package.installed,
+            outdated: package.outdated,
+            deprecated: package.deprecated,
+            downloads_365d: package.analytics.downloads_365d,
+            popularity: package.analytics.popularity,
+            installed_time: installed_time(package),
+            install_size: package.install_size,
+            warning_count: package.warnings.len(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PackageListItem {
+    Summary(PackageSummary),
+    Full(Box<BrewPackage>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackagePage {
+    pub items: Vec<PackageListItem>,
+    /// Packages matching the filters, across all pages
+    pub total_count: usize,
+    pub total_installed: usize,
+    pub total_outdated: usize,
+    /// Position of the first item among the matching packages
+    pub offset: usize,
+    /// Pass back as `cursor` for the next page; `None` on the last one
+    pub next_cursor: Option<String>,
+    /// Marks data served from the last good snapshot while offline
+    pub freshness: Option<CatalogFreshness>,
+}
+
+impl PackageQuery {
+    /// Filters, sorts and slices `packages`. Ties are broken by name, so
+    /// pages never overlap or skip packages while the listing is unchanged.
+    pub fn run(&self, packages: &[BrewPackage]) -> Result<PackagePage, BrewDeckError> {
+        let mut matching: Vec<&BrewPackage> = packages
+            .iter()
+            .filter(|package| self.filters.matches(package))
+            .collect();
+
+        let direction = self.direction.unwrap_or(self.sort.default_direction());
+        matching.sort_by(|a, b| compare(self.sort, direction, a, b).then_with(|| a.name.cmp(&b.name)));
+
+        let start = match &self.cursor {
+            Some(cursor) => resume_position(&matching, cursor)?,
+            None => self.offset,
+        }
+        .min(matching.len());
+        let page_size = self.page_size.clamp(1, MAX_PAGE_SIZE);
+        let end = (start + page_size).min(matching.len());
+
+        let page = &matching[start..end];
+        let next_cursor = page
+            .last()
+            .filter(|_| end < matching.len())
+            .map(|last| encode_cursor(end, &last.name));
+
+        Ok(PackagePage {
+            items: page
+                .iter()
+                .map(|package| match self.projection {
+                    Projection::Summary => PackageListItem::Summary(PackageSummary::from(*package)),
+                    Projection::Full => PackageListItem::Full(Box::new((*package).clone())),
+                })
+                .collect(),
+            total_count: matching.len(),
+            total_installed: matching.iter().filter(|package| package.installed).count(),
+            total_outdated: matching.iter().filter(|package| package.outdated).count(),
+            offset: start,
+            next_cursor,
+            freshness: None,
+        })
+    }
+}
+
+fn installed_time(package: &BrewPackage) -> Option<DateTime<Utc>> {
+    package.installation.as_ref().and_then(|installation| installation.installed_time)
+}
+
+/// Packages without an install date or size sort last in either direction
+fn compare(key: PackageSortKey, direction: SortDirection, a: &BrewPackage, b: &BrewPackage) -> Ordering {
+    fn directed<T: Ord>(a: T, b: T, direction: SortDirection) -> Ordering {
+        match direction {
+            SortDirection::Ascending => a.cmp(&b),
+            SortDirection::Descending => b.cmp(&a),
+        }
+    }
+
+    fn missing_last<T: Ord>(a: Option<T>, b: Option<T>, direction: SortDirection) -> Ordering {
+        match (a, b) {
+            (Some(a), Some(b)) => directed(a, b, direction),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    }
+
+    match key {
+        PackageSortKey::Name => directed(&a.name, &b.name, direction),
+        PackageSortKey::Popularity => directed(a.analytics.downloads_365d, b.analytics.downloads_365d, direction),
+        PackageSortKey::InstallDate => missing_last(installed_time(a), installed_time(b), direction),
+        PackageSortKey::Size => missing_last(a.install_size, b.install_size, direction),
+    }
+}
+
+/// A cursor remembers the last package handed out and where the next page
+/// started, so a page still lines up when packages before it came or went
+fn encode_cursor(next_offset: usize, last_name: &str) -> String {
+    URL_SAFE_NO_PAD.encode(format!("{next_offset}:{last_name}"))
+}
+
+fn resume_position(matching: &[&BrewPackage], cursor: &str) -> Result<usize, BrewDeckError> {
+    let invalid = || BrewDeckError::ParsingError(format!("Invalid page cursor '{cursor}'"));
+
+    let decoded = URL_SAFE_NO_PAD.decode(cursor).map_err(|_| invalid())?;
+    let decoded = String::from_utf8(decoded).map_err(|_| invalid())?;
+    let (offset, last_name) = decoded.split_once(':').ok_or_else(invalid)?;
+    let offset: usize = offset.parse().map_err(|_| invalid())?;
+
+    // The last package may have dropped out, e.g. uninstalled under an
+    // installed filter; the offset is the best guess then
+    Ok(matching
+        .iter()
+        .position(|package| package.name == last_name)
+        .map_or(offset, |position| position + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::installed_state::InstalledPackage;
+    use crate::services::package_service::PackageAnalytics;
+    use chrono::TimeZone;
+
+    fn package(name: &str, license: Option<&str>, downloads_365d: u64) -> BrewPackage {
+        BrewPackage {
+            name: name.to_string(),
+            version: "1.0".to_string(),
+            description: String::new(),
+            installed: false,
+            outdated: false,
+            homepage: String::new(),
+            license: license.map(str::to_string),
+            dependencies: Vec::new(),
+            linux_dependencies: Vec::new(),
+            conflicts: Vec::new(),
+            caveats: String::new(),
+            analytics: PackageAnalytics {
+                downloads_365d,
+                ..PackageAnalytics::default()
+            },
+            category: None,
+            warnings: Vec::new(),
+            install_size: None,
+            last_updated: None,
+            package_type: PackageType::Formula,
+            tap: Some("homebrew/core".to_string()),
+            deprecated: false,
+            auto_updates: false,
+            installation: None,
+            bottle: None,
+            resolved_from: None,
+            search_match: None,
+        }
+    }
+
+    fn installed(mut package: BrewPackage, installed_time: Option<(i32, u32)>, install_size: Option<u64>) -> BrewPackage {
+        let mut installation = InstalledPackage::from_name(&package.name, package.package_type);
+        installation.installed_time = installed_time.map(|(year, month)| Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0).unwrap());
+
+        package.installed = true;
+        package.installation = Some(installation);
+        package.install_size = install_size;
+        package
+    }
+
+    fn fixtures() -> Vec<BrewPackage> {
+        let mut curl = installed(package("curl", Some("curl"), 900), Some((2024, 1)), None);
+        curl.outdated = true;
+        let mut legacy = package("legacy", Some("Custom license"), 10);
+        legacy.deprecated = true;
+        legacy.tap = Some("user/tools".to_string());
+
+        vec![
+            installed(package("wget", Some("GPL-3.0-or-later"), 500), Some((2024, 3)), Some(4_000_000)),
+            curl,
+            package("jq", Some("MIT"), 300),
+            installed(package("ripgrep", Some("MIT OR Unlicense"), 700), None, Some(2_000_000)),
+            legacy,
+        ]
+    }
+
+    fn names(page: &PackagePage) -> Vec<&str> {
+        page.items
+            .iter()
+            .map(|item| match item {
+                PackageListItem::Summary(summary) => summary.name.as_str(),
+                PackageListItem::Full(package) => package.name.as_str(),
+            })
+            .collect()
+    }
+
+    fn sorted(sort: PackageSortKey, direction: Option<SortDirection>) -> Vec<String> {
+        let query = PackageQuery {
+            sort,
+            direction,
+            ..PackageQuery::default()
+        };
+        let page = query.run(&fixtures()).unwrap();
+        names(&page).into_iter().map(str::to_string).collect()
+    }
+
+    fn filtered(filters: PackageFilters) -> Vec<String> {
+        let query = PackageQuery {
+            filters,
+            ..PackageQuery::default()
+        };
+        let page = query.run(&fixtures()).unwrap();
+        names(&page).into_iter().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn applies_filters() {
+        assert_eq!(
+            filtered(PackageFilters { installed: Some(true), ..PackageFilters::default() }),
+            vec!["curl", "ripgrep", "wget"]
+        );
+        assert_eq!(filtered(PackageFilters { outdated: Some(true), ..PackageFilters::default() }), vec!["curl"]);
+        assert_eq!(filtered(PackageFilters { deprecated: Some(true), ..PackageFilters::default() }), vec!["legacy"]);
+        assert_eq!(
+            filtered(PackageFilters { tap: Some("HOMEBREW/CORE".to_string()), ..PackageFilters::default() }),
+            vec!["curl", "jq", "ripgrep", "wget"]
+        );
+        assert_eq!(
+            filtered(PackageFilters { license: Some("mit".to_string()), ..PackageFilters::default() }),
+            vec!["jq", "ripgrep"]
+        );
+        // An unparseable expression is compared as a whole
+        assert_eq!(
+            filtered(PackageFilters { license: Some("custom license".to_string()), ..PackageFilters::default() }),
+            vec!["legacy"]
+        );
+        assert_eq!(
+            filtered(PackageFilters { installed: Some(true), min_downloads: Some(600), ..PackageFilters::default() }),
+            vec!["curl", "ripgrep"]
+        );
+    }
+
+    #[test]
+    fn reports_totals_over_every_matching_package() {
+        let query = PackageQuery {
+            page_size: 1,
+            ..PackageQuery::default()
+        };
+        let page = query.run(&fixtures()).unwrap();
+
+        assert_eq!(page.items.len(), 1);
+        assert_eq!((page.total_count, page.total_installed, page.total_outdated), (5, 3, 1));
+    }
+
+    #[test]
+    fn sorts_in_either_direction() {
+        assert_eq!(sorted(PackageSortKey::Name, None), vec!["curl", "jq", "legacy", "ripgrep", "wget"]);
+        assert_eq!(
+            sorted(PackageSortKey::Name, Some(SortDirection::Descending)),
+            vec!["wget", "ripgrep", "legacy", "jq", "curl"]
+        );
+        assert_eq!(sorted(PackageSortKey::Popularity, None), vec!["curl", "ripgrep", "wget", "jq", "legacy"]);
+        assert_eq!(
+            sorted(PackageSortKey::Popularity, Some(SortDirection::Ascending)),
+            vec!["legacy", "jq", "wget", "ripgrep", "curl"]
+        );
+    }
+
+    #[test]
+    fn sorts_missing_install_date_and_size_last() {
+        assert_eq!(sorted(PackageSortKey::InstallDate, None), vec!["wget", "curl", "jq", "legacy", "ripgrep"]);
+        assert_eq!(
+            sorted(PackageSortKey::InstallDate, Some(SortDirection::Ascending)),
+            vec!["curl", "wget", "jq", "legacy", "ripgrep"]
+        );
+        assert_eq!(sorted(PackageSortKey::Size, None), vec!["wget", "ripgrep", "curl", "jq", "legacy"]);
+        assert_eq!(
+            sorted(PackageSortKey::Size, Some(SortDirection::Ascending)),
+            vec!["ripgrep", "wget", "curl", "jq", "legacy"]
+        );
+    }
+
+    #[test]
+    fn clamps_page_size() {
+        let packages: Vec<BrewPackage> = (0..MAX_PAGE_SIZE + 20)
+            .map(|i| package(&format!("formula-{i:04}"), None, 0))
+            .collect();
+
+        let smallest_page = PackageQuery { page_size: 0, ..PackageQuery::default() }.run(&packages).unwrap();
+        assert_eq!(smallest_page.items.len(), 1);
+
+        let huge_page = PackageQuery { page_size: 10_000, ..PackageQuery::default() }.run(&packages).unwrap();
+        assert_eq!(huge_page.items.len(), MAX_PAGE_SIZE);
+        assert!(huge_page.next_cursor.is_some());
+    }
+
+    #[test]
+    fn resumes_from_cursor() {
+        let packages = fixtures();
+        let mut query = PackageQuery {
+            page_size: 2,
+            ..PackageQuery::default()
+        };
+        let mut pages = Vec::new();
+
+        loop {
+            let page = query.run(&packages).unwrap();
+            pages.push((page.offset, names(&page).join(",")));
+            match page.next_cursor {
+                Some(cursor) => query.cursor = Some(cursor),
+                None => break,
+            }
+        }
+
+        assert_eq!(
+            pages,
+            vec![(0, "curl,jq".to_string()), (2, "legacy,ripgrep".to_string()), (4, "wget".to_string())]
+        );
+    }
+
+    #[test]
+    fn cursor_survives_packages_dropping_out() {
+        let first_page = PackageQuery { page_size: 2, ..PackageQuery::default() }.run(&fixtures()).unwrap();
+        let cursor = first_page.next_cursor.unwrap();
+        let next_page = |packages: &[BrewPackage]| {
+            let query = PackageQuery {
+                page_size: 2,
+                cursor: Some(cursor.clone()),
+                ..PackageQuery::default()
+            };
+            query.run(packages).unwrap()
+        };
+
+        // A package before the cursor went away; the page still starts right
+        // after the last one handed out
+        let without_curl: Vec<BrewPackage> = fixtures().into_iter().filter(|package| package.name != "curl").collect();
+        assert_eq!(names(&next_page(&without_curl)), vec!["legacy", "ripgrep"]);
+
+        // The last package handed out went away; the stored offset is used
+        let without_jq: Vec<BrewPackage> = fixtures().into_iter().filter(|package| package.name != "jq").collect();
+        let page = next_page(&without_jq);
+        assert_eq!(page.offset, 2);
+        assert_eq!(names(&page), vec!["ripgrep", "wget"]);
+    }
+
+    #[test]
+    fn rejects_malformed_cursor() {
+        let query = PackageQuery {
+            cursor: Some("not a cursor".to_string()),
+            ..PackageQuery::default()
+        };
+
+        assert!(matches!(query.run(&fixtures()), Err(BrewDeckError::ParsingError(_))));
+        assert!(resume_position(&[], &URL_SAFE_NO_PAD.encode("wget")).is_err());
+    }
+}
@@ -1,10 +1,32 @@
 use crate::error::{BrewDeckError, with_fallback};
 use crate::services::{BrewClient, CacheManager};
-use crate::services::brew_client::PackageType;
+use crate::services::api_client::{ApiClient, ApiEndpointConfig, ApiPackageInfo, CatalogResponse, PackageParser};
+use crate::services::brew_client::{BrewEnvironment, GreedyMode, PackageType, UpdateSummary};
+use crate::services::analytics::{self, AnalyticsSnapshot, AnalyticsStore};
+use crate::services::catalog_index::{canonical_name, CatalogIndex, PackageRedirect};
+use crate::services::catalog_store::{CatalogMetadata, CatalogParseStats, CatalogStatus, CatalogStore};
+use crate::services::connectivity::{CatalogFreshness, ConnectivityMonitor, OfflineStatus};
+use crate::services::disk_usage::{DiskUsageCalculator, DiskUsageSummary};
+use crate::services::fs_watcher::{PackageChangeEvent, PrefixWatcher};
+use crate::services::http_client::{HttpClientFactory, HttpSettings};
+use crate::services::installed_state::{InstalledPackage, InstalledStateReader};
+use crate::services::license::{LicensePolicy, LicensePolicyMode, LicenseReport, LicenseStatus};
+use crate::services::package_query::{PackagePage, PackageQuery};
+use crate::services::platform::{HostPlatform, PlatformCapabilities};
+use crate::services::query_language::QueryExpression;
+use crate::services::rate_limiter::RateLimiter;
+use crate::services::search::{MatchKind, SearchMatch, SearchQuery};
+use crate::services::search_index::SearchIndex;
+use crate::services::signature::{JwsVerifier, SignatureConfig};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::RwLock;
 use tracing::{debug, info, warn};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,7 +37,11 @@ pub struct BrewPackage {
     pub installed: bool,
     pub outdated: bool,
     pub homepage: String,
+    /// SPDX license expression, e.g. `Apache-2.0 OR MIT`
+    pub license: Option<String>,
     pub dependencies: Vec<String>,
+    /// `uses_from_macos` dependencies brew installs on Linux; empty on macOS
+    pub linux_dependencies: Vec<String>,
     pub conflicts: Vec<String>,
     pub caveats: String,
     pub analytics: PackageAnalytics,
@@ -24,12 +50,33 @@ pub struct BrewPackage {
     pub install_size: Option<u64>,
     pub last_updated: Option<DateTime<Utc>>,
     pub package_type: PackageType,
+    /// e.g. `homebrew/core`; `None` when only `brew info` was available
+    pub tap: Option<String>,
+    pub deprecated: bool,
+    pub auto_updates: bool,
+    pub installation: Option<InstalledPackage>,
+    /// Prebuilt bottle for this host; `None` for casks and packages not from the API
+    pub bottle: Option<BottleAvailability>,
+    /// Set when the package was looked up by an alias, old name or full name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved_from: Option<PackageRedirect>,
+    /// Score and matched fields when the package came from a search
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub search_match: Option<SearchMatch>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PackageAnalytics {
     pub downloads_365d: u64,
+    pub installs: AnalyticsCounts,
+    pub installs_on_request: AnalyticsCounts,
+    pub build_errors: AnalyticsCounts,
+    /// Build errors per install over 365 days; formulae only
+    pub build_error_rate: Option<f64>,
+    /// Percentile of 365 day installs among packages of the same type, 0-100
     pub popularity: f64,
+    /// Position by 365 day installs, 1 being the most installed
+    pub popularity_rank: Option<u32>,
     pub rating: Option<f64>,
 }
 
@@ -37,12 +84,35 @@ impl Default for PackageAnalytics {
     fn default() -> Self {
         Self {
             downloads_365d: 0,
+            installs: AnalyticsCounts::default(),
+            installs_on_request: AnalyticsCounts::default(),
+            build_errors: AnalyticsCounts::default(),
+            build_error_rate: None,
             popularity: 0.0,
+            popularity_rank: None,
             rating: None,
         }
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BottleAvailability {
+    /// The host's bottle tag, e.g. `arm64_sonoma` or `x86_64_linux`
+    pub host_tag: String,
+    pub available: bool,
+    /// Tag of the bottle brew would pour: the host's, an older macOS release's or `all`
+    pub tag: Option<String>,
+    pub url: Option<String>,
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AnalyticsCounts {
+    pub days_30: u64,
+    pub days_90: u64,
+    pub days_365: u64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PackageWarning {
     pub warning_type: WarningType,
@@ -58,6 +128,7 @@ pub enum WarningType {
     Experimental,
     RequiresRoot,
     ConflictsWith,
+    License,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -74,60 +145,412 @@ pub struct PackageSearchResult {
     pub search_time_ms: u64,
 }
 
+/// Formulae and casks matching one query, ranked together
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnifiedSearchResult {
+    pub packages: Vec<BrewPackage>,
+    pub total_count: usize,
+    pub facets: SearchFacets,
+    pub search_time_ms: u64,
+}
+
+/// Counts over the matched packages, for filtering them in the UI
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SearchFacets {
+    pub package_types: BTreeMap<PackageType, usize>,
+    /// Packages without a known tap are not counted
+    pub taps: BTreeMap<String, usize>,
+    pub installed: usize,
+    pub not_installed: usize,
+    pub deprecated: usize,
+}
+
+impl SearchFacets {
+    fn count<'a>(packages: impl IntoIterator<Item = &'a BrewPackage>) -> Self {
+        let mut facets = Self::default();
+        
+        for package in packages {
+            facets.add(package.package_type, package.tap.as_deref(), package.installed, package.deprecated);
+        }
+        
+        facets
+    }
+    
+    fn add(&mut self, package_type: PackageType, tap: Option<&str>, installed: bool, deprecated: bool) {
+        *self.package_types.entry(package_type).or_default() += 1;
+        if let Some(tap) = tap {
+            *self.taps.entry(tap.to_string()).or_default() += 1;
+        }
+        if installed {
+            self.installed += 1;
+        } else {
+            self.not_installed += 1;
+        }
+        if deprecated {
+            self.deprecated += 1;
+        }
+    }
+    
+    fn merge(&mut self, other: SearchFacets) {
+        for (package_type, count) in other.package_types {
+            *self.package_types.entry(package_type).or_default() += count;
+        }
+        for (tap, count) in other.taps {
+            *self.taps.entry(tap).or_default() += count;
+        }
+        self.installed += other.installed;
+        self.not_installed += other.not_installed;
+        self.deprecated += other.deprecated;
+    }
+}
+
+/// The best-ranked matches of a search, with the count and facets of all
+/// of them
+#[derive(Debug, Default)]
+struct SearchHits {
+    packages: Vec<BrewPackage>,
+    total_count: usize,
+    facets: SearchFacets,
+}
+
+impl SearchHits {
+    fn extend(&mut self, other: SearchHits) {
+        self.packages.extend(other.packages);
+        self.total_count += other.total_count;
+        self.facets.merge(other.facets);
+    }
+}
+
+/// For lists counted as they are, e.g. the few packages the brew fallback
+/// looks up
+impl From<Vec<BrewPackage>> for SearchHits {
+    fn from(packages: Vec<BrewPackage>) -> Self {
+        Self {
+            total_count: packages.len(),
+            facets: SearchFacets::count(&packages),
+            packages,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaskUpgradeSettings {
+    pub greedy_mode: GreedyMode,
+    /// Casks that are always upgraded with `--greedy`, whatever `greedy_mode` says
+    pub greedy_casks: Vec<String>,
+}
+
+impl CaskUpgradeSettings {
+    /// Where the settings are kept unless configured otherwise
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("BrewDeck").join("cask-upgrades.json"))
+    }
+    
+    /// Reads the settings file; a missing file means the defaults
+    pub fn load(path: &Path) -> Result<Self, BrewDeckError> {
+        match std::fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| {
+                BrewDeckError::InvalidConfiguration(format!("Invalid cask upgrade settings {}: {}", path.display(), e))
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+    
+    pub fn save(&self, path: &Path) -> Result<(), BrewDeckError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        
+        let temp_path = path.with_extension("json.tmp");
+        std::fs::write(&temp_path, serde_json::to_vec_pretty(self)?)?;
+        std::fs::rename(&temp_path, path)?;
+        
+        info!("Saved cask upgrade settings to {}", path.display());
+        Ok(())
+    }
+    
+    /// Whether a greedy upgrade picks up the cask, as `brew outdated --cask --greedy` would
+    pub fn is_greedy(&self, token: &str, auto_updates: bool, version_latest: bool) -> bool {
+        self.greedy_mode == GreedyMode::All
+            || (self.greedy_mode == GreedyMode::AutoUpdates && auto_updates)
+            || (self.greedy_mode == GreedyMode::Latest && version_latest)
+            || self.greedy_casks.iter().any(|cask| cask == token)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaskOutdatedReport {
+    /// Casks `brew outdated --cask` reports
+    pub outdated: Vec<String>,
+    /// Installed casks brew skips without `--greedy`, so they may have drifted
+    pub possibly_stale: Vec<StaleCask>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StaleCask {
+    pub name: String,
+    pub version: String,
+    pub auto_updates: bool,
+    pub version_latest: bool,
+    pub greedy_opt_in: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InstallResult {
     pub success: bool,
     pub message: String,
     pub package_name: String,
     pub duration_ms: u64,
+    /// Raised before the operation started, e.g. source builds
+    pub warnings: Vec<PackageWarning>,
+}
+
+/// Most results a catalog search returns
+const MAX_SEARCH_RESULTS: usize = 50;
+
+/// Most packages an advanced query returns; the count and facets cover all matches
+const MAX_ADVANCED_SEARCH_RESULTS: usize = 500;
+
+/// How long a parsed catalog is reused before it is revalidated with the server
+const CATALOG_REVALIDATE_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Rough source build estimate: a fixed cost plus a few minutes for every
+/// build dependency, which tracks how heavy a build tends to be
+const SOURCE_BUILD_BASE_MINUTES: u32 = 3;
+const SOURCE_BUILD_MINUTES_PER_BUILD_DEPENDENCY: u32 = 2;
+
+/// Above this a source build warning is raised as high severity
+const LONG_SOURCE_BUILD_MINUTES: u32 = 30;
+
+/// Best match first; results without a score, from `brew search`, keep
+/// brew's order after scored ones. Ties go to formulae, then by name, so
+/// the order never depends on how the results were gathered.
+fn compare_search_results(a: &BrewPackage, b: &BrewPackage) -> Ordering {
+    let score = |package: &BrewPackage| package.search_match.as_ref().map(|search_match| search_match.score);
+    
+    match (score(a), score(b)) {
+        (Some(a_score), Some(b_score)) => b_score
+            .total_cmp(&a_score)
+            .then_with(|| a.package_type.cmp(&b.package_type))
+            .then_with(|| a.name.cmp(&b.name)),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => a.package_type.cmp(&b.package_type),
+    }
+}
+
+/// What `parse_api_package` needs beyond the package itself
+struct ParseContext<'a> {
+    installed: &'a HashMap<String, InstalledPackage>,
+    /// Formula catalog used to canonicalize dependency names
+    dependency_index: Option<&'a CatalogIndex>,
+    analytics: Option<&'a AnalyticsSnapshot>,
+    /// Decides whether auto-updating casks count as outdated
+    cask_upgrades: &'a CaskUpgradeSettings,
+}
+
+struct LoadedCatalog {
+    index: Arc<CatalogIndex>,
+    validated_at: std::time::Instant,
+    parse_stats: CatalogParseStats,
+    freshness: CatalogFreshness,
+}
+
+struct CatalogLoad {
+    /// The catalog to parse, streamed from disk; `None` when the caller's
+    /// parsed copy is still the latest available
+    body: Option<std::fs::File>,
+    /// Served from disk because the network is unavailable
+    stale: bool,
+    as_of: Option<DateTime<Utc>>,
 }
 
 pub struct PackageService {
+    platform: &'static HostPlatform,
     cache: Arc<CacheManager>,
     brew_client: Arc<BrewClient>,
-    api_client: reqwest::Client,
+    http: Arc<HttpClientFactory>,
+    http_settings_path: Option<PathBuf>,
+    rate_limiter: Arc<RateLimiter>,
+    api_client: ApiClient,
+    api_endpoints_path: Option<PathBuf>,
+    catalog_store: Option<CatalogStore>,
+    catalogs: RwLock<HashMap<PackageType, LoadedCatalog>>,
+    search_indexes: RwLock<HashMap<PackageType, Arc<SearchIndex>>>,
+    package_lists: RwLock<HashMap<PackageType, Arc<Vec<BrewPackage>>>>,
+    analytics: AnalyticsStore,
+    connectivity: ConnectivityMonitor,
+    signature_config: RwLock<SignatureConfig>,
+    signature_config_path: Option<PathBuf>,
+    license_policy: RwLock<LicensePolicy>,
+    license_policy_path: Option<PathBuf>,
+    last_update: RwLock<Option<UpdateSummary>>,
+    last_update_path: Option<PathBuf>,
+    cask_upgrade_settings: RwLock<CaskUpgradeSettings>,
+    cask_upgrade_settings_path: Option<PathBuf>,
+    disk_usage: DiskUsageCalculator,
+    installed_state: InstalledStateReader,
+    watcher: RwLock<Option<PrefixWatcher>>,
 }
 
 impl PackageService {
     pub async fn new(cache: Arc<CacheManager>) -> Result<Self, BrewDeckError> {
         let brew_client = Arc::new(BrewClient::new().await?);
-        let api_client = reqwest::Client::builder()
-            .timeout(Duration::from_secs(30))
-            .user_agent("BrewDeck/1.0")
-            .build()
-            .map_err(|e| BrewDeckError::NetworkError(e.to_string()))?;
+        let installed_state = InstalledStateReader::new(brew_client.prefix());
+        let http_settings_path = HttpSettings::default_path();
+        let http_settings = match &http_settings_path {
+            Some(path) => HttpSettings::load(path).unwrap_or_else(|e| {
+                warn!("Ignoring HTTP settings: {}", e);
+                HttpSettings::default()
+            }),
+            None => HttpSettings::default(),
+        };
+        // e.g. a CA bundle that has since been deleted
+        let http = match HttpClientFactory::new(http_settings) {
+            Ok(http) => http,
+            Err(e) => {
+                warn!("Ignoring HTTP settings that no longer apply: {}", e);
+                HttpClientFactory::new(HttpSettings::default())?
+            }
+        };
+        let http = Arc::new(http);
+        let rate_limiter = Arc::new(RateLimiter::default());
+        let api_endpoints_path = ApiEndpointConfig::default_path();
+        let api_endpoints = match &api_endpoints_path {
+            Some(path) => ApiEndpointConfig::load(path).unwrap_or_else(|e| {
+                warn!("Ignoring API endpoints: {}", e);
+                ApiEndpointConfig::from_env()
+            }),
+            None => ApiEndpointConfig::from_env(),
+        };
+        let api_client = ApiClient::with_endpoints(Arc::clone(&http), Arc::clone(&rate_limiter), api_endpoints)?;
+        let catalog_store = cache.config().persistence_dir().map(CatalogStore::new);
+        let signature_config_path = SignatureConfig::default_path();
+        let signature_config = match &signature_config_path {
+            Some(path) => SignatureConfig::load(path).unwrap_or_else(|e| {
+                warn!("Ignoring signature settings: {}", e);
+                SignatureConfig::default()
+            }),
+            None => SignatureConfig::default(),
+        };
+        if signature_config.enabled {
+            // Verification stays on without a key, so catalogs are refused rather than trusted
+            let repository = Self::brew_repository(&brew_client).await;
+            match JwsVerifier::from_config(&signature_config, &repository) {
+                Ok(verifier) => api_client.set_verifier(verifier).await,
+                Err(e) => warn!("Cannot verify catalog signatures: {}", e),
+            }
+        }
+        let license_policy_path = LicensePolicy::default_path();
+        let license_policy = match &license_policy_path {
+            Some(path) => LicensePolicy::load(path).unwrap_or_else(|e| {
+                warn!("Ignoring license policy: {}", e);
+                LicensePolicy::default()
+            }),
+            None => LicensePolicy::default(),
+        };
+        let cask_upgrade_settings_path = CaskUpgradeSettings::default_path();
+        let cask_upgrade_settings = match &cask_upgrade_settings_path {
+            Some(path) => CaskUpgradeSettings::load(path).unwrap_or_else(|e| {
+                warn!("Ignoring cask upgrade settings: {}", e);
+                CaskUpgradeSettings::default()
+            }),
+            None => CaskUpgradeSettings::default(),
+        };
+        let last_update_path = UpdateSummary::default_path();
+        let last_update = match &last_update_path {
+            Some(path) => UpdateSummary::load(path).unwrap_or_else(|e| {
+                warn!("Ignoring last update summary: {}", e);
+                None
+            }),
+            None => None,
+        };
         
         Ok(Self {
+            platform: HostPlatform::current(),
             cache,
             brew_client,
+            http,
+            http_settings_path,
+            rate_limiter,
             api_client,
+            api_endpoints_path,
+            catalog_store,
+            catalogs: RwLock::new(HashMap::new()),
+            search_indexes: RwLock::new(HashMap::new()),
+            package_lists: RwLock::new(HashMap::new()),
+            analytics: AnalyticsStore::new(),
+            connectivity: ConnectivityMonitor::new(),
+            signature_config: RwLock::new(signature_config),
+            signature_config_path,
+            license_policy: RwLock::new(license_policy),
+            license_policy_path,
+            last_update: RwLock::new(last_update),
+            last_update_path,
+            cask_upgrade_settings: RwLock::new(cask_upgrade_settings),
+            cask_upgrade_settings_path,
+            disk_usage: DiskUsageCalculator::new(),
+            installed_state,
+            watcher: RwLock::new(None),
         })
     }
     
-    pub async fn get_packages(&self, package_type: PackageType) -> Result<Vec<BrewPackage>, BrewDeckError> {
+    pub async fn get_packages(&self, package_type: PackageType) -> Result<Arc<Vec<BrewPackage>>, BrewDeckError> {
+        self.platform.ensure_supported(package_type)?;
+        
         let cache_key = format!("packages_{package_type}");
         
-        // Try to get from cache first
-        if let Some(cached_packages) = self.cache.get::<Vec<BrewPackage>>(&cache_key).await {
-            debug!("Retrieved {} packages from cache", cached_packages.len());
-            return Ok(cached_packages);
+        // The list itself stays in memory; the cache entry only tracks its TTL
+        // and tag invalidation so the list isn't serialized and cloned per hit
+        if self.cache.get::<usize>(&cache_key).await.is_some() {
+            if let Some(cached_packages) = self.package_lists.read().await.get(&package_type) {
+                debug!("Retrieved {} packages from cache", cached_packages.len());
+                return Ok(Arc::clone(cached_packages));
+            }
         }
         
         // Fetch from API with fallback to local brew commands
-        let packages = with_fallback(
+        let mut packages = with_fallback(
             || self.fetch_packages_from_api(package_type),
             || self.fetch_packages_from_brew(package_type),
         ).await?;
         
+        self.apply_install_sizes(&mut packages).await;
+        let packages = Arc::new(packages);
+        
         // Cache the results
-        let cache_tags = vec![format!("packages"), format!("type_{}", package_type)];
-        self.cache.set_with_tags(&cache_key, &packages, Some(Duration::from_secs(300)), cache_tags).await?;
+        self.package_lists.write().await.insert(package_type, Arc::clone(&packages));
+        let cache_tags = vec![format!("packages"), format!("type_{}", package_type), cache_key.clone()];
+        self.cache.set_with_tags(&cache_key, packages.len(), Some(Duration::from_secs(300)), cache_tags).await?;
         
         info!("Retrieved {} {} packages", packages.len(), package_type);
         Ok(packages)
     }
     
+    /// One page of the listing, filtered and sorted here so the UI only
+    /// receives the rows it shows
+    pub async fn query_packages(&self, package_type: PackageType, query: &PackageQuery) -> Result<PackagePage, BrewDeckError> {
+        let packages = self.get_packages(package_type).await?;
+        
+        let mut page = query.run(&packages)?;
+        page.freshness = self.catalog_freshness(package_type).await;
+        
+        debug!(
+            "Queried {} {} packages: {} match, returning {} from {}",
+            packages.len(),
+            package_type,
+            page.total_count,
+            page.items.len(),
+            page.offset
+        );
+        Ok(page)
+    }
+    
     pub async fn search_packages(&self, query: &str, package_type: PackageType) -> Result<PackageSearchResult, BrewDeckError> {
+        self.platform.ensure_supported(package_type)?;
+        
         let start_time = std::time::Instant::now();
         let cache_key = format!("search_{package_type}_{query}");
         
@@ -138,15 +561,15 @@ impl PackageService {
         }
         
         // Search using API with fallback to brew search
-        let packages = with_fallback(
+        let hits = with_fallback(
             || self.search_packages_api(query, package_type),
             || self.search_packages_brew(query, package_type),
         ).await?;
         
         let search_time_ms = start_time.elapsed().as_millis() as u64;
         let result = PackageSearchResult {
-            total_count: packages.len(),
-            packages,
+            total_count: hits.total_count,
+            packages: hits.packages,
             search_time_ms,
         };
         
@@ -157,7 +580,80 @@ impl PackageService {
         Ok(result)
     }
     
+    /// Searches formulae and casks (where supported) in one call, ranked
+    /// together by score, then formulae before casks, then name
+    pub async fn search_all_packages(&self, query: &str) -> Result<UnifiedSearchResult, BrewDeckError> {
+        let start_time = std::time::Instant::now();
+        let package_types = self.platform.supported_package_types();
+        let cache_key = format!("search_all_{query}");
+        
+        if let Some(cached_result) = self.cache.get::<UnifiedSearchResult>(&cache_key).await {
+            debug!("Retrieved unified search results from cache for query: {}", query);
+            return Ok(cached_result);
+        }
+        
+        let mut hits = with_fallback(
+            || self.search_all_packages_api(query, &package_types),
+            || self.search_all_packages_brew(query, &package_types),
+        ).await?;
+        hits.packages.sort_by(compare_search_results);
+        
+        let result = UnifiedSearchResult {
+            total_count: hits.total_count,
+            facets: hits.facets,
+            packages: hits.packages,
+            search_time_ms: start_time.elapsed().as_millis() as u64,
+        };
+        
+        let mut cache_tags = vec![format!("search")];
+        cache_tags.extend(package_types.iter().map(|package_type| format!("type_{package_type}")));
+        self.cache.set_with_tags(&cache_key, &result, Some(Duration::from_secs(60)), cache_tags).await?;
+        
+        Ok(result)
+    }
+    
+    /// Evaluates a query such as `installed:true license:MIT deps:<5` against
+    /// every supported catalog. Matches come most downloaded first.
+    pub async fn advanced_search(&self, query: &str) -> Result<UnifiedSearchResult, BrewDeckError> {
+        let start_time = std::time::Instant::now();
+        let expression = QueryExpression::parse(query)?;
+        
+        let mut listings = Vec::new();
+        for package_type in self.platform.supported_package_types() {
+            listings.push(self.get_packages(package_type).await?);
+        }
+        
+        let mut matches: Vec<&BrewPackage> = listings
+            .iter()
+            .flat_map(|packages| packages.iter())
+            .filter(|package| expression.matches(package))
+            .collect();
+        matches.sort_by(|a, b| {
+            b.analytics
+                .downloads_365d
+                .cmp(&a.analytics.downloads_365d)
+                .then_with(|| a.package_type.cmp(&b.package_type))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        
+        let result = UnifiedSearchResult {
+            total_count: matches.len(),
+            facets: SearchFacets::count(matches.iter().copied()),
+            packages: matches
+                .into_iter()
+                .take(MAX_ADVANCED_SEARCH_RESULTS)
+                .cloned()
+                .collect(),
+            search_time_ms: start_time.elapsed().as_millis() as u64,
+        };
+        
+        debug!("Query '{}' matched {} packages in {}ms", query, result.total_count, result.search_time_ms);
+        Ok(result)
+    }
+    
     pub async fn get_package_details(&self, name: &str, package_type: PackageType) -> Result<BrewPackage, BrewDeckError> {
+        self.platform.ensure_supported(package_type)?;
+        
         let cache_key = format!("package_{package_type}_{name}");
         
         // Try cache first
@@ -167,23 +663,250 @@ impl PackageService {
         }
         
         // Fetch detailed information with fallback
-        let package = with_fallback(
+        let mut package = with_fallback(
             || self.fetch_package_details_api(name, package_type),
             || self.fetch_package_details_brew(name, package_type),
         ).await?;
         
+        if package.installed {
+            match self.disk_usage.package_usage(self.brew_client.prefix(), &package.name, package_type).await {
+                Ok(usage) => package.install_size = usage.map(|usage| usage.bytes),
+                Err(e) => warn!("Failed to compute install size for {}: {}", name, e),
+            }
+        }
+        
         // Cache package details
-        let cache_tags = vec![format!("package_details"), format!("type_{}", package_type), name.to_string()];
+        let mut cache_tags = vec![format!("package_details"), format!("type_{}", package_type), name.to_string(), cache_key.clone()];
+        if package.name != name {
+            // Lookups through an alias or old name are invalidated along with the canonical entry
+            cache_tags.push(format!("package_{package_type}_{}", package.name));
+        }
         self.cache.set_with_tags(&cache_key, &package, Some(Duration::from_secs(600)), cache_tags).await?;
         
         Ok(package)
     }
     
+    /// Warnings to show before installing the formula or any dependency it
+    /// pulls in: source builds without a bottle for this host, and licenses
+    /// the license policy does not accept
+    pub async fn install_warnings(&self, name: &str, package_type: PackageType) -> Result<Vec<PackageWarning>, BrewDeckError> {
+        self.platform.ensure_supported(package_type)?;
+        
+        if package_type == PackageType::Cask {
+            let index = self.catalog_index(PackageType::Cask).await?;
+            let Some((info, _)) = index.resolve(name) else {
+                return Err(BrewDeckError::PackageNotFound(name.to_string()));
+            };
+            return Ok(Self::requirement_warnings(info));
+        }
+        
+        let index = self.catalog_index(PackageType::Formula).await?;
+        let installed = self.installed_packages(PackageType::Formula).await;
+        let Some((root, _)) = index.resolve(name) else {
+            return Err(BrewDeckError::PackageNotFound(name.to_string()));
+        };
+        
+        let plan = self.install_plan(&index, &installed, root);
+        let mut warnings = Self::source_build_warnings(&plan);
+        warnings.extend(self.license_warnings(&plan).await);
+        
+        Ok(warnings)
+    }
+    
+    fn requirement_warnings(info: &ApiPackageInfo) -> Vec<PackageWarning> {
+        info.unmet_requirements
+            .iter()
+            .map(|requirement| PackageWarning {
+                warning_type: WarningType::Compatibility,
+                message: format!("{} cannot be installed on this machine: {}", canonical_name(info), requirement),
+                severity: WarningSeverity::High,
+            })
+            .collect()
+    }
+    
+    /// Everything brew would install for `root`, root first: the formula and
+    /// its missing dependencies, plus the build dependencies of whatever has
+    /// no bottle for this host and, off macOS, `uses_from_macos`
+    /// dependencies. Each entry notes whether it builds from source.
+    fn install_plan<'a>(
+        &self,
+        index: &'a CatalogIndex,
+        installed: &HashMap<String, InstalledPackage>,
+        root: &'a ApiPackageInfo,
+    ) -> Vec<(&'a ApiPackageInfo, bool)> {
+        let mut plan = Vec::new();
+        let mut seen = HashSet::from([canonical_name(root).to_string()]);
+        let mut queue = std::collections::VecDeque::from([root]);
+        
+        while let Some(info) = queue.pop_front() {
+            let builds_from_source = info.bottle.as_ref().is_some_and(|bottle| !bottle.available);
+            let build_dependencies = if builds_from_source { info.build_dependencies.as_slice() } else { &[] };
+            let system_dependencies = if self.platform.installs_uses_from_macos() { info.uses_from_macos.as_slice() } else { &[] };
+            plan.push((info, builds_from_source));
+            
+            for dependency in info.dependencies.iter().chain(build_dependencies).chain(system_dependencies) {
+                let Some((dependency_info, _)) = index.resolve(dependency) else {
+                    continue;
+                };
+                let dependency_name = canonical_name(dependency_info);
+                if installed.contains_key(dependency_name) || !seen.insert(dependency_name.to_string()) {
+                    continue;
+                }
+                queue.push_back(dependency_info);
+            }
+        }
+        
+        plan
+    }
+    
+    fn source_build_warnings(plan: &[(&ApiPackageInfo, bool)]) -> Vec<PackageWarning> {
+        let Some((root, _)) = plan.first() else {
+            return Vec::new();
+        };
+        let root_name = canonical_name(root);
+        let host_tag = plan
+            .iter()
+            .find_map(|(info, _)| info.bottle.as_ref())
+            .map(|bottle| bottle.host_tag.as_str())
+            .unwrap_or_default();
+        
+        let estimate = |info: &ApiPackageInfo| {
+            SOURCE_BUILD_BASE_MINUTES + SOURCE_BUILD_MINUTES_PER_BUILD_DEPENDENCY * info.build_dependencies.len() as u32
+        };
+        let severity = |minutes: u32| {
+            if minutes > LONG_SOURCE_BUILD_MINUTES {
+                WarningSeverity::High
+            } else {
+                WarningSeverity::Medium
+            }
+        };
+        
+        let mut warnings = Vec::new();
+        if let Some((root, true)) = plan.first() {
+            let minutes = estimate(root);
+            warnings.push(PackageWarning {
+                warning_type: WarningType::Compatibility,
+                message: format!(
+                    "{root_name} has no bottle for {host_tag} and will be built from source (about {minutes} min)"
+                ),
+                severity: severity(minutes),
+            });
+        }
+        
+        let dependency_builds: Vec<&ApiPackageInfo> = plan
+            .iter()
+            .skip(1)
+            .filter(|(_, builds_from_source)| *builds_from_source)
+            .map(|(info, _)| *info)
+            .collect();
+        
+        if !dependency_builds.is_empty() {
+            let minutes: u32 = dependency_builds.iter().map(|info| estimate(info)).sum();
+            let names: Vec<&str> = dependency_builds.iter().map(|info| canonical_name(info)).collect();
+            warnings.push(PackageWarning {
+                warning_type: WarningType::Compatibility,
+                message: format!(
+                    "{} {} of {root_name} will be built from source for {host_tag}: {} (about {minutes} min)",
+                    names.len(),
+                    if names.len() == 1 { "dependency" } else { "dependencies" },
+                    names.join(", ")
+                ),
+                severity: severity(minutes),
+            });
+        }
+        
+        warnings
+    }
+    
+    async fn license_warnings(&self, plan: &[(&ApiPackageInfo, bool)]) -> Vec<PackageWarning> {
+        let policy = self.license_policy.read().await.clone();
+        if !policy.mode.warns() {
+            return Vec::new();
+        }
+        
+        let root_name = plan.first().map(|(root, _)| canonical_name(root)).unwrap_or_default();
+        let severity = match policy.mode {
+            LicensePolicyMode::Block => WarningSeverity::High,
+            _ => WarningSeverity::Medium,
+        };
+        
+        plan.iter()
+            .filter_map(|(info, _)| {
+                let check = policy.check(info.license.as_deref());
+                if !policy.is_violation(&check) {
+                    return None;
+                }
+                
+                let name = canonical_name(info);
+                let subject = if name == root_name {
+                    name.to_string()
+                } else {
+                    format!("{name} (dependency of {root_name})")
+                };
+                let reason = match check.status {
+                    LicenseStatus::Violation => format!("is licensed under {}", check.violations.join(", ")),
+                    LicenseStatus::Unparseable => format!("has an unrecognized license: {}", info.license.as_deref().unwrap_or_default()),
+                    _ => "declares no license".to_string(),
+                };
+                
+                Some(PackageWarning {
+                    warning_type: WarningType::License,
+                    message: format!("{subject} {reason}, which the license policy does not accept"),
+                    severity: severity.clone(),
+                })
+            })
+            .collect()
+    }
+    
     pub async fn install_package(&self, name: &str, package_type: PackageType) -> Result<InstallResult, BrewDeckError> {
+        self.platform.ensure_supported(package_type)?;
+        
         let start_time = std::time::Instant::now();
         
         info!("Installing {} package: {}", package_type, name);
         
+        // Only formulae carry licenses; a blocking policy that could not be
+        // checked must not let the install through
+        let license_mode = self.license_policy.read().await.mode;
+        let license_enforced = license_mode.blocks() && package_type == PackageType::Formula;
+        
+        let warnings = match self.install_warnings(name, package_type).await {
+            Ok(warnings) => warnings,
+            Err(e) if license_enforced => {
+                warn!("Refusing to install {}: license policy could not be checked: {}", name, e);
+                return Ok(InstallResult {
+                    success: false,
+                    message: format!("Blocked by license policy: {name} could not be checked: {e}"),
+                    package_name: name.to_string(),
+                    duration_ms: start_time.elapsed().as_millis() as u64,
+                    warnings: Vec::new(),
+                });
+            }
+            Err(e) => {
+                debug!("Could not check {} for install warnings: {}", name, e);
+                Vec::new()
+            }
+        };
+        for warning in &warnings {
+            warn!("{}", warning.message);
+        }
+        
+        let license_violations: Vec<&str> = warnings
+            .iter()
+            .filter(|warning| matches!(warning.warning_type, WarningType::License))
+            .map(|warning| warning.message.as_str())
+            .collect();
+        
+        if license_enforced && !license_violations.is_empty() {
+            return Ok(InstallResult {
+                success: false,
+                message: format!("Blocked by license policy: {}", license_violations.join("; ")),
+                package_name: name.to_string(),
+                duration_ms: start_time.elapsed().as_millis() as u64,
+                warnings,
+            });
+        }
+        
         let result = self.brew_client.install_package(name, package_type).await;
         let duration_ms = start_time.elapsed().as_millis() as u64;
         
@@ -197,6 +920,7 @@ impl PackageService {
                     message,
                     package_name: name.to_string(),
                     duration_ms,
+                    warnings,
                 }
             }
             Err(e) => InstallResult {
@@ -204,6 +928,7 @@ impl PackageService {
                 message: e.to_string(),
                 package_name: name.to_string(),
                 duration_ms,
+                warnings,
             }
         };
         
@@ -211,6 +936,8 @@ impl PackageService {
     }
     
     pub async fn uninstall_package(&self, name: &str, package_type: PackageType) -> Result<InstallResult, BrewDeckError> {
+        self.platform.ensure_supported(package_type)?;
+        
         let start_time = std::time::Instant::now();
         
         info!("Uninstalling {} package: {}", package_type, name);
@@ -228,6 +955,7 @@ impl PackageService {
                     message,
                     package_name: name.to_string(),
                     duration_ms,
+                    warnings: Vec::new(),
                 }
             }
             Err(e) => InstallResult {
@@ -235,6 +963,7 @@ impl PackageService {
                 message: e.to_string(),
                 package_name: name.to_string(),
                 duration_ms,
+                warnings: Vec::new(),
             }
         };
         
@@ -242,6 +971,8 @@ impl PackageService {
     }
     
     pub async fn update_package(&self, name: &str, package_type: PackageType) -> Result<InstallResult, BrewDeckError> {
+        self.platform.ensure_supported(package_type)?;
+        
         let start_time = std::time::Instant::now();
         
         info!("Updating {} package: {}", package_type, name);
@@ -259,6 +990,7 @@ impl PackageService {
                     message,
                     package_name: name.to_string(),
                     duration_ms,
+                    warnings: Vec::new(),
                 }
             }
             Err(e) => InstallResult {
@@ -266,44 +998,723 @@ impl PackageService {
                 message: e.to_string(),
                 package_name: name.to_string(),
                 duration_ms,
+                warnings: Vec::new(),
             }
         };
         
         Ok(install_result)
     }
     
-    async fn fetch_packages_from_api(&self, package_type: PackageType) -> Result<Vec<BrewPackage>, BrewDeckError> {
-        let api_url = match package_type {
-            PackageType::Formula => "https://formulae.brew.sh/api/formula.json",
-            PackageType::Cask => "https://formulae.brew.sh/api/cask.json",
+    pub async fn update_all_packages(&self, package_type: PackageType) -> Result<String, BrewDeckError> {
+        self.platform.ensure_supported(package_type)?;
+        
+        info!("Updating all {} packages", package_type);
+        
+        let message = match package_type {
+            PackageType::Formula => {
+                self.brew_client.update_all(Some(PackageType::Formula), GreedyMode::Off).await?
+            }
+            PackageType::Cask => {
+                let settings = self.cask_upgrade_settings.read().await.clone();
+                let mut message = self.brew_client.update_all(Some(PackageType::Cask), settings.greedy_mode).await?;
+                
+                // Opted-in casks still need a greedy pass unless everything was already greedy.
+                // brew rejects the whole pass over a cask that is not installed, so those are
+                // skipped, and a failure is reported without undoing the upgrade above.
+                if settings.greedy_mode != GreedyMode::All && !settings.greedy_casks.is_empty() {
+                    let installed = self.installed_packages(PackageType::Cask).await;
+                    let greedy_casks: Vec<String> = settings
+                        .greedy_casks
+                        .iter()
+                        .filter(|token| installed.contains_key(token.as_str()))
+                        .cloned()
+                        .collect();
+                    
+                    if !greedy_casks.is_empty() {
+                        match self.brew_client.upgrade_casks_greedy(&greedy_casks).await {
+                            Ok(greedy_message) => message = format!("{message}; {greedy_message}"),
+                            Err(e) => {
+                                warn!("Greedy upgrade of {} failed: {}", greedy_casks.join(", "), e);
+                                message = format!("{message}; greedy upgrade failed: {e}");
+                            }
+                        }
+                    }
+                }
+                
+                message
+            }
         };
         
-        debug!("Fetching packages from API: {}", api_url);
+        let tags = vec![format!("type_{package_type}"), "search".to_string()];
+        self.cache.invalidate_by_tags(&tags).await;
+        
+        Ok(message)
+    }
+    
+    pub async fn get_cask_upgrade_settings(&self) -> CaskUpgradeSettings {
+        self.cask_upgrade_settings.read().await.clone()
+    }
+    
+    /// Replaces the cask upgrade settings and saves them to the settings file
+    pub async fn update_cask_upgrade_settings(&self, settings: CaskUpgradeSettings) -> Result<(), BrewDeckError> {
+        if let Some(path) = &self.cask_upgrade_settings_path {
+            settings.save(path)?;
+        }
+        
+        info!("Cask upgrade settings updated: {:?}", settings);
+        *self.cask_upgrade_settings.write().await = settings;
+        
+        // Whether auto-updating casks count as outdated depends on the settings
+        let tags = vec![format!("type_{}", PackageType::Cask), "search".to_string()];
+        self.cache.invalidate_by_tags(&tags).await;
+        Ok(())
+    }
+    
+    pub async fn get_cask_outdated_report(&self) -> Result<CaskOutdatedReport, BrewDeckError> {
+        let packages = self.get_packages(PackageType::Cask).await?;
+        let settings = self.cask_upgrade_settings.read().await.clone();
         
-        let response = self.api_client.get(api_url).send().await?;
-        let api_data: Vec<serde_json::Value> = response.json().await?;
+        let mut outdated = Vec::new();
+        let mut possibly_stale = Vec::new();
         
-        // Get installed and outdated packages
-        let installed = self.brew_client.list_installed(package_type).await.unwrap_or_default();
-        let outdated = self.brew_client.list_outdated(package_type).await.unwrap_or_default();
+        for package in packages.iter().filter(|p| p.installed) {
+            let version_latest = package.version == "latest";
+            
+            if package.outdated {
+                outdated.push(package.name.clone());
+            } else if package.auto_updates || version_latest {
+                possibly_stale.push(StaleCask {
+                    greedy_opt_in: settings.is_greedy(&package.name, package.auto_updates, version_latest),
+                    name: package.name.clone(),
+                    version: package.version.clone(),
+                    auto_updates: package.auto_updates,
+                    version_latest,
+                });
+            }
+        }
+        
+        Ok(CaskOutdatedReport {
+            outdated,
+            possibly_stale,
+        })
+    }
+    
+    /// Watches the prefix for changes made outside BrewDeck, e.g. `brew install`
+    /// in a terminal, invalidating the affected caches before calling `on_change`.
+    pub async fn start_watching<F>(&self, on_change: F) -> Result<(), BrewDeckError>
+    where
+        F: Fn(PackageChangeEvent) + Send + Sync + 'static,
+    {
+        let watcher = PrefixWatcher::start(
+            self.brew_client.prefix(),
+            Arc::clone(&self.cache),
+            Duration::from_millis(1500),
+            on_change,
+        )?;
         
-        let packages = api_data
+        *self.watcher.write().await = Some(watcher);
+        Ok(())
+    }
+    
+    pub async fn get_disk_usage_summary(&self) -> Result<DiskUsageSummary, BrewDeckError> {
+        self.disk_usage.summary(self.brew_client.prefix()).await
+    }
+    
+    async fn apply_install_sizes(&self, packages: &mut [BrewPackage]) {
+        if !packages.iter().any(|p| p.installed) {
+            return;
+        }
+        
+        let summary = match self.disk_usage.summary(self.brew_client.prefix()).await {
+            Ok(summary) => summary,
+            Err(e) => {
+                warn!("Failed to compute install sizes: {}", e);
+                return;
+            }
+        };
+        
+        let sizes: HashMap<_, _> = summary
+            .packages
             .into_iter()
-            .map(|data| self.parse_api_package(data, package_type, &installed, &outdated))
-            .collect::<Result<Vec<_>, _>>()?;
+            .map(|usage| ((usage.package_type, usage.name), usage.bytes))
+            .collect();
+        
+        for package in packages.iter_mut().filter(|p| p.installed) {
+            package.install_size = sizes.get(&(package.package_type, package.name.clone())).copied();
+        }
+    }
+    
+    pub async fn update_homebrew(&self) -> Result<UpdateSummary, BrewDeckError> {
+        info!("Updating Homebrew");
+        
+        let summary = self.brew_client.update_homebrew().await?;
+        
+        // New and updated formulae change both listings and details
+        let tags = vec!["packages".to_string(), "package_details".to_string(), "search".to_string()];
+        let invalidated = self.cache.invalidate_by_tags(&tags).await;
+        info!(
+            "Homebrew updated: {} new, {} updated, {} deleted formulae; invalidated {} cache entries",
+            summary.new_formulae.len(),
+            summary.updated_formulae_count,
+            summary.deleted_formulae.len(),
+            invalidated
+        );
+        
+        // Homebrew did update, so a summary that cannot be saved is only logged
+        if let Some(path) = &self.last_update_path {
+            if let Err(e) = summary.save(path) {
+                warn!("Failed to save update summary: {}", e);
+            }
+        }
+        
+        *self.last_update.write().await = Some(summary.clone());
+        Ok(summary)
+    }
+    
+    pub async fn last_update_summary(&self) -> Option<UpdateSummary> {
+        self.last_update.read().await.clone()
+    }
+    
+    pub async fn get_api_endpoints(&self) -> ApiEndpointConfig {
+        self.api_client.endpoints().await
+    }
+    
+    /// Applies new endpoints and saves them, so mirrors survive a restart
+    pub async fn set_api_endpoints(&self, endpoints: ApiEndpointConfig) -> Result<(), BrewDeckError> {
+        self.api_client.set_endpoints(endpoints.clone()).await?;
+        if let Some(path) = &self.api_endpoints_path {
+            endpoints.save(path)?;
+        }
+        
+        // Catalog data from the previous endpoint may differ from the new one
+        self.reset_catalogs().await;
+        
+        Ok(())
+    }
+    
+    /// The limiter every API request goes through, for background work to
+    /// check before adding to the load
+    pub fn rate_limiter(&self) -> &RateLimiter {
+        &self.rate_limiter
+    }
+    
+    /// The HTTP settings with the proxy password redacted
+    pub async fn get_http_settings(&self) -> HttpSettings {
+        self.http.settings().await.redacted()
+    }
+    
+    /// Applies new HTTP settings and saves them to the settings file. Redacted
+    /// credentials, as `get_http_settings` returns them, keep their current value.
+    pub async fn set_http_settings(&self, mut settings: HttpSettings) -> Result<(), BrewDeckError> {
+        settings.restore_redacted(&self.http.settings().await);
+        
+        // Applied first so settings that cannot build a client are never saved
+        self.http.update(settings.clone()).await?;
+        if let Some(path) = &self.http_settings_path {
+            settings.save(path)?;
+        }
+        
+        // A proxy or CA change may be what was keeping requests from succeeding
+        if self.connectivity.is_network_unreachable().await && self.api_client.probe().await.is_ok() {
+            self.connectivity.record_success().await;
+            self.refresh_pending_catalogs().await;
+        }
+        
+        Ok(())
+    }
+    
+    pub async fn get_license_policy(&self) -> LicensePolicy {
+        self.license_policy.read().await.clone()
+    }
+    
+    /// Replaces the license policy and saves it to the policy file
+    pub async fn set_license_policy(&self, policy: LicensePolicy) -> Result<(), BrewDeckError> {
+        if let Some(path) = &self.license_policy_path {
+            policy.save(path)?;
+        }
+        
+        info!(
+            "License policy updated: {:?} mode, {} allowed, {} denied",
+            policy.mode,
+            policy.allow.len(),
+            policy.deny.len()
+        );
+        *self.license_policy.write().await = policy;
+        Ok(())
+    }
+    
+    /// License inventory of every installed formula, checked against the policy
+    pub async fn get_license_report(&self) -> Result<LicenseReport, BrewDeckError> {
+        let packages = self.get_packages(PackageType::Formula).await?;
+        let policy = self.license_policy.read().await.clone();
+        
+        let installed = packages
+            .iter()
+            .filter(|package| package.installed)
+            .map(|package| {
+                let version = package
+                    .installation
+                    .as_ref()
+                    .and_then(|installation| installation.current_version.clone())
+                    .unwrap_or_else(|| package.version.clone());
+                (package.name.clone(), version, package.license.clone())
+            })
+            .collect();
+        
+        Ok(LicenseReport::build(policy, installed))
+    }
+    
+    pub async fn get_signature_config(&self) -> SignatureConfig {
+        self.signature_config.read().await.clone()
+    }
+    
+    /// Checks that the key loads, saves the settings, then applies them
+    pub async fn set_signature_config(&self, config: SignatureConfig) -> Result<(), BrewDeckError> {
+        let repository = Self::brew_repository(&self.brew_client).await;
+        let verifier = JwsVerifier::from_config(&config, &repository)?;
+        if let Some(path) = &self.signature_config_path {
+            config.save(path)?;
+        }
+        info!("Catalog signature verification {}", if verifier.is_some() { "enabled" } else { "disabled" });
+        
+        self.api_client.set_verifier(verifier).await;
+        *self.signature_config.write().await = config;
+        
+        // Catalogs parsed before the change were not checked the same way
+        self.reset_catalogs().await;
+        
+        Ok(())
+    }
+    
+    /// brew keeps its key in the repository, which is not the prefix on Intel macOS
+    async fn brew_repository(brew_client: &BrewClient) -> PathBuf {
+        brew_client
+            .get_brew_environment()
+            .await
+            .ok()
+            .and_then(|environment| environment.repository)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| brew_client.prefix().to_path_buf())
+    }
+    
+    async fn reset_catalogs(&self) {
+        self.catalogs.write().await.clear();
+        self.analytics.invalidate().await;
+        
+        let tags = vec!["packages".to_string(), "package_details".to_string(), "search".to_string()];
+        self.cache.invalidate_by_tags(&tags).await;
+    }
+    
+    pub async fn get_catalog_status(&self) -> Vec<CatalogStatus> {
+        let mut statuses = Vec::new();
+        
+        for package_type in self.platform.supported_package_types() {
+            let mut status = match &self.catalog_store {
+                Some(store) => store.status(package_type).await,
+                None => CatalogStore::unavailable_status(package_type),
+            };
+            status.last_parse = self.catalogs
+                .read()
+                .await
+                .get(&package_type)
+                .map(|loaded| loaded.parse_stats.clone());
+            statuses.push(status);
+        }
+        
+        statuses
+    }
+    
+    /// Freshness of the parsed catalog, if one has been loaded
+    pub async fn catalog_freshness(&self, package_type: PackageType) -> Option<CatalogFreshness> {
+        self.catalogs
+            .read()
+            .await
+            .get(&package_type)
+            .map(|loaded| loaded.freshness.clone())
+    }
+    
+    pub async fn get_offline_status(&self) -> OfflineStatus {
+        let catalogs = self.catalogs
+            .read()
+            .await
+            .values()
+            .map(|loaded| loaded.freshness.clone())
+            .collect();
+        
+        self.connectivity.status(catalogs).await
+    }
+    
+    pub async fn set_offline_mode(&self, offline: bool) -> OfflineStatus {
+        self.connectivity.set_manual(offline).await;
+        
+        if !offline {
+            self.refresh_pending_catalogs().await;
+        }
+        
+        self.get_offline_status().await
+    }
+    
+    /// Probes the API while the network is considered unreachable and runs
+    /// the queued refreshes once it answers. `on_change` is called whenever
+    /// BrewDeck goes offline or comes back.
+    pub async fn monitor_connectivity<F>(self: Arc<Self>, interval: Duration, on_change: F)
+    where
+        F: Fn(OfflineStatus) + Send + Sync + 'static,
+    {
+        let mut was_offline = self.connectivity.is_offline().await;
+        
+        loop {
+            tokio::time::sleep(interval).await;
+            
+            if self.connectivity.is_network_unreachable().await {
+                match self.api_client.probe().await {
+                    Ok(()) => {
+                        self.connectivity.record_success().await;
+                    }
+                    Err(e) => debug!("API still unreachable: {}", e),
+                }
+            }
+            
+            let offline = self.connectivity.is_offline().await;
+            if offline == was_offline {
+                continue;
+            }
+            was_offline = offline;
+            
+            if !offline {
+                self.refresh_pending_catalogs().await;
+            }
+            on_change(self.get_offline_status().await);
+        }
+    }
+    
+    async fn analytics_snapshot(&self, package_type: PackageType) -> Option<Arc<AnalyticsSnapshot>> {
+        if self.connectivity.is_offline().await {
+            return self.analytics.cached(package_type).await;
+        }
+        
+        self.analytics.snapshot(&self.api_client, package_type).await
+    }
+    
+    async fn refresh_pending_catalogs(&self) {
+        if self.connectivity.is_offline().await {
+            return;
+        }
+        
+        for package_type in self.connectivity.take_pending_refreshes().await {
+            info!("Refreshing {} catalog now that the network is back", package_type);
+            
+            // Cached lists, details and searches were built from the stale snapshot
+            self.cache.invalidate_by_tags(&[format!("type_{package_type}")]).await;
+            
+            // A failure here queues the refresh again
+            if let Err(e) = self.catalog_index(package_type).await {
+                warn!("Failed to refresh {} catalog: {}", package_type, e);
+            }
+        }
+    }
+    
+    pub fn get_platform_capabilities(&self) -> PlatformCapabilities {
+        self.platform.capabilities()
+    }
+    
+    pub async fn get_brew_environment(&self) -> Result<BrewEnvironment, BrewDeckError> {
+        self.brew_client.get_brew_environment().await
+    }
+    
+    async fn fetch_packages_from_api(&self, package_type: PackageType) -> Result<Vec<BrewPackage>, BrewDeckError> {
+        debug!("Fetching {} packages from API", package_type);
+        
+        let index = self.catalog_index(package_type).await?;
+        let installed = self.installed_packages(package_type).await;
+        let analytics = self.analytics_snapshot(package_type).await;
+        let cask_upgrades = self.cask_upgrade_settings.read().await.clone();
+        
+        // Cask dependencies are formulae, so only formula lists can canonicalize them here
+        let context = ParseContext {
+            installed: &installed,
+            dependency_index: (package_type == PackageType::Formula).then_some(index.as_ref()),
+            analytics: analytics.as_deref(),
+            cask_upgrades: &cask_upgrades,
+        };
+        
+        let packages = index
+            .packages()
+            .iter()
+            .map(|info| self.parse_api_package(info, package_type, &context))
+            .collect();
         
         Ok(packages)
     }
     
+    /// The parsed catalog for `package_type`, reused until it is due for
+    /// revalidation and rebuilt only when the server has a new version.
+    async fn catalog_index(&self, package_type: PackageType) -> Result<Arc<CatalogIndex>, BrewDeckError> {
+        let current = self.catalogs.read().await.get(&package_type).map(|loaded| {
+            // Stale snapshots are rechecked on every access so they are
+            // replaced as soon as the network is back
+            let fresh = !loaded.freshness.stale && loaded.validated_at.elapsed() < CATALOG_REVALIDATE_INTERVAL;
+            (Arc::clone(&loaded.index), loaded.parse_stats.clone(), loaded.freshness.as_of, fresh)
+        });
+        
+        if let Some((index, _, _, true)) = &current {
+            return Ok(Arc::clone(index));
+        }
+        
+        let load = self.load_catalog(package_type, current.is_some()).await?;
+        let (index, parse_stats, previous_as_of) = match (load.body, current) {
+            (Some(body), _) => {
+                let (index, parse_stats) = Self::parse_catalog(package_type, body, self.platform).await?;
+                self.sync_search_index(package_type, Arc::clone(&index)).await;
+                (index, parse_stats, None)
+            }
+            (None, Some((index, parse_stats, as_of, _))) => (index, parse_stats, Some(as_of)),
+            (None, None) => return Err(BrewDeckError::InternalError(
+                format!("No {package_type} catalog available to reuse")
+            )),
+        };
+        
+        let freshness = CatalogFreshness {
+            package_type,
+            stale: load.stale,
+            as_of: load.as_of.or(previous_as_of).unwrap_or_else(Utc::now),
+        };
+        
+        self.catalogs.write().await.insert(package_type, LoadedCatalog {
+            index: Arc::clone(&index),
+            validated_at: std::time::Instant::now(),
+            parse_stats,
+            freshness,
+        });
+        
+        Ok(index)
+    }
+    
+    /// Parses and indexes a catalog off the async runtime
+    async fn parse_catalog(
+        package_type: PackageType,
+        body: std::fs::File,
+        host: &'static HostPlatform,
+    ) -> Result<(Arc<CatalogIndex>, CatalogParseStats), BrewDeckError> {
+        tokio::task::spawn_blocking(move || {
+            let start_time = std::time::Instant::now();
+            
+            let bytes = body.metadata()?.len();
+            let infos = PackageParser::parse_catalog(package_type, BufReader::new(body), host)?;
+            
+            let parse_stats = CatalogParseStats {
+                bytes,
+                records: infos.len(),
+                duration_ms: start_time.elapsed().as_millis() as u64,
+                parsed_at: Utc::now(),
+            };
+            info!(
+                "Parsed {} {} records from {} bytes in {}ms",
+                parse_stats.records,
+                package_type,
+                parse_stats.bytes,
+                parse_stats.duration_ms
+            );
+            
+            Ok((Arc::new(CatalogIndex::build(package_type, infos)), parse_stats))
+        })
+        .await
+        .map_err(|e| BrewDeckError::InternalError(format!("Catalog parse task failed: {e}")))?
+    }
+    
+    /// Brings the search index in line with a newly parsed catalog, starting
+    /// from the copy on disk on first use, and persists it when a package
+    /// was added, changed or removed.
+    async fn sync_search_index(&self, package_type: PackageType, catalog: Arc<CatalogIndex>) {
+        let current = self.search_indexes.read().await.get(&package_type).cloned();
+        let path = self.catalog_store.as_ref().map(|store| store.search_index_path(package_type));
+        
+        let synced = tokio::task::spawn_blocking(move || {
+            let start_time = std::time::Instant::now();
+            let mut search_index = match current {
+                Some(index) => Arc::unwrap_or_clone(index),
+                None => path
+                    .as_deref()
+                    .and_then(|path| SearchIndex::load(path, package_type))
+                    .unwrap_or_else(|| SearchIndex::new(package_type)),
+            };
+            
+            let changes = search_index.sync(catalog.packages());
+            info!(
+                "Synced {} search index in {:?}: {} added, {} updated, {} removed",
+                package_type,
+                start_time.elapsed(),
+                changes.added,
+                changes.updated,
+                changes.removed
+            );
+            
+            if let Some(path) = path.filter(|_| !changes.is_empty()) {
+                if let Err(e) = search_index.save(&path) {
+                    warn!("Failed to store {} search index: {}", package_type, e);
+                }
+            }
+            search_index
+        })
+        .await;
+        
+        match synced {
+            Ok(search_index) => {
+                self.search_indexes.write().await.insert(package_type, Arc::new(search_index));
+            }
+            Err(e) => warn!("{} search index task failed: {}", package_type, e),
+        }
+    }
+    
+    /// Returns the raw catalog, revalidating the copy on disk with a
+    /// conditional request and downloading it only when it changed. While
+    /// offline the stored copy is served as-is and a refresh is queued.
+    ///
+    /// With `have_parsed` set, an unchanged catalog comes back without a body
+    /// so the caller can keep its parsed copy instead of reading it back.
+    async fn load_catalog(&self, package_type: PackageType, have_parsed: bool) -> Result<CatalogLoad, BrewDeckError> {
+        let offline = self.connectivity.is_offline().await;
+        
+        // Nothing to read from disk when offline with a parsed copy in memory
+        if offline && have_parsed {
+            self.connectivity.queue_refresh(package_type).await;
+            return Ok(CatalogLoad { body: None, stale: true, as_of: None });
+        }
+        
+        let stored = match &self.catalog_store {
+            Some(store) => store.load(package_type).await,
+            None => None,
+        };
+        
+        // A copy stored before verification was turned on, or checked against
+        // another key, is neither served offline nor revalidated with a 304
+        let signature_config = self.signature_config.read().await.clone();
+        let stored = stored.filter(|stored| {
+            let trusted = !signature_config.enabled
+                || stored.metadata.verified_by.as_deref() == Some(signature_config.key_id.as_str());
+            if !trusted {
+                warn!("Ignoring the stored {} catalog: its signature was not verified", package_type);
+            }
+            trusted
+        });
+        
+        // Downloads go next to the stored copy so saving them is a rename
+        let download_path = match &self.catalog_store {
+            Some(store) => store.download_path(package_type).await.ok(),
+            None => None,
+        }
+        .unwrap_or_else(|| std::env::temp_dir().join(format!("brewdeck-{package_type}.json.tmp")));
+        
+        let result = if offline {
+            Err(BrewDeckError::NetworkError("Offline mode is on".to_string()))
+        } else {
+            self.api_client
+                .fetch_catalog(package_type, stored.as_ref().map(|stored| &stored.metadata), &download_path)
+                .await
+        };
+        
+        let response = match result {
+            Ok(response) => {
+                self.connectivity.record_success().await;
+                response
+            }
+            Err(e) => {
+                if !offline {
+                    self.connectivity.record_failure(&e).await;
+                }
+                
+                let Some(stored) = stored.filter(|_| ConnectivityMonitor::can_serve_snapshot(&e)) else {
+                    return Err(e);
+                };
+                
+                // A failing server is retried on the next access instead
+                if ConnectivityMonitor::indicates_offline(&e) {
+                    self.connectivity.queue_refresh(package_type).await;
+                }
+                warn!(
+                    "Serving the {} catalog from {} while unable to refresh it: {}",
+                    package_type, stored.metadata.validated_at, e
+                );
+                
+                return Ok(CatalogLoad {
+                    as_of: Some(stored.metadata.validated_at),
+                    body: (!have_parsed).then_some(stored.body),
+                    stale: true,
+                });
+            }
+        };
+        
+        match (response, stored) {
+            (CatalogResponse::NotModified, Some(stored)) => {
+                debug!("Stored {} catalog is still current", package_type);
+                if let Some(store) = &self.catalog_store {
+                    if let Err(e) = store.mark_validated(package_type).await {
+                        warn!("Failed to record {} catalog validation: {}", package_type, e);
+                    }
+                }
+                Ok(CatalogLoad {
+                    body: (!have_parsed).then_some(stored.body),
+                    stale: false,
+                    as_of: Some(Utc::now()),
+                })
+            }
+            (CatalogResponse::NotModified, None) => Err(BrewDeckError::NetworkError(
+                format!("Server answered 304 for the {package_type} catalog without a stored copy")
+            )),
+            (CatalogResponse::Modified { verified_by, .. }, _)
+                if signature_config.enabled && verified_by.as_deref() != Some(signature_config.key_id.as_str()) =>
+            {
+                Err(BrewDeckError::SignatureVerificationFailed(format!(
+                    "The {package_type} catalog could not be verified with key {}",
+                    signature_config.key_id
+                )))
+            }
+            (CatalogResponse::Modified { source_url, size_bytes, etag, last_modified, verified_by }, _) => {
+                info!("Downloaded {} catalog ({} bytes) from {}", package_type, size_bytes, source_url);
+                
+                let mut stored_body = None;
+                if let Some(store) = &self.catalog_store {
+                    let now = Utc::now();
+                    let metadata = CatalogMetadata {
+                        package_type,
+                        source_url,
+                        etag,
+                        last_modified,
+                        fetched_at: now,
+                        validated_at: now,
+                        size_bytes,
+                        verified_by,
+                    };
+                    match store.save_download(&metadata, &download_path).await {
+                        Ok(()) => stored_body = store.load(package_type).await.map(|stored| stored.body),
+                        Err(e) => warn!("Failed to store {} catalog: {}", package_type, e),
+                    }
+                }
+                
+                // Without a store the download is parsed where it landed
+                let body = match stored_body {
+                    Some(file) => file,
+                    None => std::fs::File::open(&download_path)?,
+                };
+                Ok(CatalogLoad {
+                    body: Some(body),
+                    stale: false,
+                    as_of: Some(Utc::now()),
+                })
+            }
+        }
+    }
+    
     async fn fetch_packages_from_brew(&self, package_type: PackageType) -> Result<Vec<BrewPackage>, BrewDeckError> {
         debug!("Fetching packages using brew commands");
         
-        let installed = self.brew_client.list_installed(package_type).await?;
-        let outdated = self.brew_client.list_outdated(package_type).await.unwrap_or_default();
+        let installed = self.installed_packages(package_type).await;
+        let mut installed_names: Vec<_> = installed.keys().cloned().collect();
+        installed_names.sort();
         
         let mut packages = Vec::new();
         
-        for package_name in installed {
+        for package_name in installed_names {
             match self.fetch_package_details_brew(&package_name, package_type).await {
                 Ok(package) => packages.push(package),
                 Err(e) => {
@@ -314,9 +1725,11 @@ impl PackageService {
                         version: "unknown".to_string(),
                         description: format!("{package_type} package"),
                         installed: true,
-                        outdated: outdated.contains(&package_name),
+                        outdated: installed[&package_name].reported_outdated.unwrap_or(false),
                         homepage: String::new(),
+                        license: None,
                         dependencies: Vec::new(),
+                        linux_dependencies: Vec::new(),
                         conflicts: Vec::new(),
                         caveats: String::new(),
                         analytics: PackageAnalytics::default(),
@@ -325,6 +1738,13 @@ impl PackageService {
                         install_size: None,
                         last_updated: None,
                         package_type,
+                        tap: None,
+                        deprecated: false,
+                        auto_updates: false,
+                        installation: installed.get(&package_name).cloned(),
+                        bottle: None,
+                        resolved_from: None,
+                        search_match: None,
                     });
                 }
             }
@@ -333,23 +1753,128 @@ impl PackageService {
         Ok(packages)
     }
     
-    async fn search_packages_api(&self, query: &str, package_type: PackageType) -> Result<Vec<BrewPackage>, BrewDeckError> {
-        let packages = self.fetch_packages_from_api(package_type).await?;
+    async fn search_packages_api(&self, query: &str, package_type: PackageType) -> Result<SearchHits, BrewDeckError> {
+        let index = self.catalog_index(package_type).await?;
+        let installed = self.installed_packages(package_type).await;
+        let analytics = self.analytics_snapshot(package_type).await;
+        let cask_upgrades = self.cask_upgrade_settings.read().await.clone();
+        let context = ParseContext {
+            installed: &installed,
+            dependency_index: (package_type == PackageType::Formula).then_some(index.as_ref()),
+            analytics: analytics.as_deref(),
+            cask_upgrades: &cask_upgrades,
+        };
+        
+        let Some(search) = SearchQuery::new(query) else {
+            return Ok(SearchHits::default());
+        };
+        
+        // What the query names outright, e.g. through an alias or `homebrew/core/wget`
+        let resolved = index.resolve(query);
+        let resolved_name = resolved.as_ref().map(|(info, _)| canonical_name(info));
+        let popularity = |name: &str| analytics.as_deref().map_or(0.0, |snapshot| snapshot.popularity(name));
+        
+        // Only packages sharing a term with the query are scored; without an
+        // index yet the whole catalog is
+        let search_index = self.search_indexes.read().await.get(&package_type).cloned();
+        let mut candidates: Vec<&ApiPackageInfo> = match &search_index {
+            Some(search_index) => search_index
+                .candidates(&search)
+                .into_iter()
+                .filter_map(|name| index.get(name))
+                .collect(),
+            None => index.packages().iter().collect(),
+        };
+        if let Some((info, _)) = resolved.as_ref().filter(|_| search_index.is_some()) {
+            if !candidates.iter().any(|candidate| canonical_name(candidate) == canonical_name(info)) {
+                candidates.push(info);
+            }
+        }
+        
+        let mut matches: Vec<(&ApiPackageInfo, SearchMatch)> = candidates
+            .into_iter()
+            .filter_map(|info| {
+                let name = canonical_name(info);
+                let search_match = search.score(info, popularity(name));
+                
+                if Some(name) == resolved_name && search_match.as_ref().is_none_or(|m| m.kind != MatchKind::Exact) {
+                    return Some((info, search.redirected(popularity(name))));
+                }
+                search_match.map(|search_match| (info, search_match))
+            })
+            .collect();
+        
+        matches.sort_by(|(a, a_match), (b, b_match)| {
+            b_match
+                .score
+                .total_cmp(&a_match.score)
+                .then_with(|| canonical_name(a).cmp(canonical_name(b)))
+        });
+        
+        // Counted before truncating, so the UI can tell how much was left out
+        let mut facets = SearchFacets::default();
+        for (info, _) in &matches {
+            let tap = Some(info.tap.as_str()).filter(|tap| !tap.is_empty());
+            facets.add(package_type, tap, installed.contains_key(canonical_name(info)), info.deprecated);
+        }
+        let total_count = matches.len();
+        matches.truncate(MAX_SEARCH_RESULTS);
         
-        let query_lower = query.to_lowercase();
-        let filtered_packages: Vec<BrewPackage> = packages
+        let redirect = resolved.and_then(|(_, redirect)| redirect);
+        
+        let packages = matches
             .into_iter()
-            .filter(|pkg| {
-                pkg.name.to_lowercase().contains(&query_lower) ||
-                pkg.description.to_lowercase().contains(&query_lower)
+            .map(|(info, search_match)| {
+                let mut package = self.parse_api_package(info, package_type, &context);
+                if redirect.as_ref().is_some_and(|redirect| redirect.canonical == package.name) {
+                    package.resolved_from = redirect.clone();
+                }
+                package.search_match = Some(search_match);
+                package
             })
-            .take(50) // Limit results
             .collect();
         
-        Ok(filtered_packages)
+        Ok(SearchHits {
+            packages,
+            total_count,
+            facets,
+        })
+    }
+    
+    async fn search_all_packages_api(&self, query: &str, package_types: &[PackageType]) -> Result<SearchHits, BrewDeckError> {
+        let mut hits = SearchHits::default();
+        for package_type in package_types {
+            hits.extend(self.search_packages_api(query, *package_type).await?);
+        }
+        
+        Ok(hits)
+    }
+    
+    /// One `brew search` for every type instead of one per type
+    async fn search_all_packages_brew(&self, query: &str, package_types: &[PackageType]) -> Result<SearchHits, BrewDeckError> {
+        let search_results = self.brew_client.search_with_types(query, None).await?;
+        
+        let mut packages = Vec::new();
+        for package_type in package_types {
+            let names = search_results
+                .iter()
+                .filter(|(_, result_type)| result_type == package_type)
+                .take(20); // Limit for performance
+            
+            for (package_name, _) in names {
+                match self.fetch_package_details_brew(package_name, *package_type).await {
+                    Ok(package) => packages.push(package),
+                    Err(e) => {
+                        warn!("Failed to get details for search result {}: {}", package_name, e);
+                    }
+                }
+            }
+        }
+        
+        Ok(packages.into())
     }
     
-    async fn search_packages_brew(&self, query: &str, package_type: PackageType) -> Result<Vec<BrewPackage>, BrewDeckError> {
+    async fn search_packages_brew(&self, query: &str, package_type: PackageType) -> Result<SearchHits, BrewDeckError> {
         let search_results = self.brew_client.search(query, Some(package_type)).await?;
         
         let mut packages = Vec::new();
@@ -362,32 +1887,37 @@ impl PackageService {
             }
         }
         
-        Ok(packages)
+        Ok(packages.into())
     }
     
     async fn fetch_package_details_api(&self, name: &str, package_type: PackageType) -> Result<BrewPackage, BrewDeckError> {
-        let api_url = match package_type {
-            PackageType::Formula => "https://formulae.brew.sh/api/formula.json",
-            PackageType::Cask => "https://formulae.brew.sh/api/cask.json",
-        };
+        let index = self.catalog_index(package_type).await?;
+        let (info, redirect) = index
+            .resolve(name)
+            .ok_or_else(|| BrewDeckError::PackageNotFound(format!("Package '{name}' not found")))?;
         
-        let response = self.api_client.get(api_url).send().await?;
-        let api_data: Vec<serde_json::Value> = response.json().await?;
+        if let Some(redirect) = &redirect {
+            debug!("Resolved {} to {} ({:?})", redirect.requested, redirect.canonical, redirect.kind);
+        }
         
-        let field_name = match package_type {
-            PackageType::Formula => "name",
-            PackageType::Cask => "token",
+        let installed = self.installed_packages(package_type).await;
+        let formula_index = match package_type {
+            PackageType::Formula => Some(Arc::clone(&index)),
+            PackageType::Cask => self.catalog_index(PackageType::Formula).await.ok(),
+        };
+        let analytics = self.analytics_snapshot(package_type).await;
+        let cask_upgrades = self.cask_upgrade_settings.read().await.clone();
+        let context = ParseContext {
+            installed: &installed,
+            dependency_index: formula_index.as_deref(),
+            analytics: analytics.as_deref(),
+            cask_upgrades: &cask_upgrades,
         };
         
-        let package_data = api_data
-            .into_iter()
-            .find(|data| data[field_name].as_str() == Some(name))
-            .ok_or_else(|| BrewDeckError::PackageNotFound(format!("Package '{name}' not found")))?;
-        
-        let installed = self.brew_client.list_installed(package_type).await.unwrap_or_default();
-        let outdated = self.brew_client.list_outdated(package_type).await.unwrap_or_default();
+        let mut package = self.parse_api_package(info, package_type, &context);
+        package.resolved_from = redirect;
         
-        self.parse_api_package(package_data, package_type, &installed, &outdated)
+        Ok(package)
     }
     
     async fn fetch_package_details_brew(&self, name: &str, package_type: PackageType) -> Result<BrewPackage, BrewDeckError> {
@@ -397,73 +1927,93 @@ impl PackageService {
     
     fn parse_api_package(
         &self,
-        data: serde_json::Value,
+        info: &ApiPackageInfo,
         package_type: PackageType,
-        installed: &[String],
-        outdated: &[String],
-    ) -> Result<BrewPackage, BrewDeckError> {
-        let name = match package_type {
-            PackageType::Formula => data["name"].as_str(),
-            PackageType::Cask => data["token"].as_str(),
-        }.ok_or_else(|| BrewDeckError::ParsingError("Missing package name".to_string()))?;
-        
-        let is_installed = installed.contains(&name.to_string());
-        let is_outdated = is_installed && outdated.contains(&name.to_string());
-        
-        let analytics = PackageAnalytics {
-            downloads_365d: data["analytics"]["install"]["365d"].as_u64().unwrap_or(0),
-            popularity: data["analytics"]["install"]["365d"].as_f64().unwrap_or(0.0) / 1000.0, // Normalize
-            rating: None,
+        context: &ParseContext,
+    ) -> BrewPackage {
+        let name = canonical_name(info).to_string();
+        
+        // Kegs are named after the version plus a `_<revision>` suffix once a formula is rebuilt
+        let installed_version = if info.revision > 0 {
+            format!("{}_{}", info.version, info.revision)
+        } else {
+            info.version.clone()
         };
         
-        let mut warnings = Vec::new();
+        let installation = context.installed.get(&name).cloned();
+        let is_installed = installation.is_some();
+        let auto_updates = info.auto_updates.unwrap_or(false);
+        let greedy = context.cask_upgrades.is_greedy(&name, auto_updates, info.version == "latest");
+        let is_outdated = installation
+            .as_ref()
+            .is_some_and(|i| i.is_outdated(&installed_version, auto_updates, greedy));
+        
+        // The catalogs carry no analytics; only single package payloads embed them
+        let analytics = match context.analytics {
+            Some(snapshot) => snapshot.package_analytics(&name),
+            None => analytics::from_embedded(package_type, &info.analytics),
+        };
         
-        // Check for deprecation
-        if data["deprecated"].as_bool().unwrap_or(false) {
+        // Dependencies may be aliases or tap-qualified names
+        let canonicalize = |names: &[String]| -> Vec<String> {
+            match context.dependency_index {
+                Some(index) => names.iter().map(|dep| index.canonical(dep).to_string()).collect(),
+                None => names.to_vec(),
+            }
+        };
+        let dependencies = canonicalize(&info.dependencies);
+        let linux_dependencies = if self.platform.installs_uses_from_macos() {
+            canonicalize(&info.uses_from_macos)
+        } else {
+            Vec::new()
+        };
+        
+        let mut warnings = PackageParser::convert_to_package_warnings(info.warnings.clone());
+        if let Some(bottle) = info.bottle.as_ref().filter(|bottle| !bottle.available) {
             warnings.push(PackageWarning {
-                warning_type: WarningType::Deprecated,
-                message: "This package is deprecated".to_string(),
-                severity: WarningSeverity::Medium,
+                warning_type: WarningType::Compatibility,
+                message: format!("No bottle for {}; installing builds from source", bottle.host_tag),
+                severity: WarningSeverity::Low,
             });
         }
-        
-        // Check for conflicts
-        if let Some(conflicts) = data["conflicts_with"].as_array() {
-            if !conflicts.is_empty() {
-                warnings.push(PackageWarning {
-                    warning_type: WarningType::ConflictsWith,
-                    message: format!("Conflicts with: {}", 
-                        conflicts.iter()
-                            .filter_map(|v| v.as_str())
-                            .collect::<Vec<_>>()
-                            .join(", ")
-                    ),
-                    severity: WarningSeverity::Low,
-                });
-            }
+        warnings.extend(Self::requirement_warnings(info));
+        if !linux_dependencies.is_empty() {
+            warnings.push(PackageWarning {
+                warning_type: WarningType::Compatibility,
+                message: format!(
+                    "Needs extra packages on Linux that macOS provides: {}",
+                    linux_dependencies.join(", ")
+                ),
+                severity: WarningSeverity::Low,
+            });
         }
         
-        Ok(BrewPackage {
-            name: name.to_string(),
-            version: data["versions"]["stable"].as_str().unwrap_or("unknown").to_string(),
-            description: data["desc"].as_str().unwrap_or("No description available").to_string(),
+        BrewPackage {
+            name,
+            version: info.version.clone(),
+            description: info.description.clone(),
             installed: is_installed,
             outdated: is_outdated,
-            homepage: data["homepage"].as_str().unwrap_or("").to_string(),
-            dependencies: data["dependencies"].as_array()
-                .map(|deps| deps.iter().filter_map(|d| d.as_str()).map(|s| s.to_string()).collect())
-                .unwrap_or_default(),
-            conflicts: data["conflicts_with"].as_array()
-                .map(|conflicts| conflicts.iter().filter_map(|c| c.as_str()).map(|s| s.to_string()).collect())
-                .unwrap_or_default(),
-            caveats: data["caveats"].as_str().unwrap_or("").to_string(),
+            homepage: info.homepage.clone().unwrap_or_default(),
+            license: info.license.clone(),
+            dependencies,
+            linux_dependencies,
+            conflicts: info.conflicts.clone(),
+            caveats: info.caveats.clone().unwrap_or_default(),
             analytics,
             category: None, // Would need additional categorization logic
             warnings,
-            install_size: None, // Not available in API
+            install_size: None, // Filled in from the Cellar by apply_install_sizes
             last_updated: None, // Would need additional parsing
             package_type,
-        })
+            tap: Some(info.tap.clone()).filter(|tap| !tap.is_empty()),
+            deprecated: info.deprecated,
+            auto_updates,
+            installation,
+            bottle: info.bottle.clone(),
+            resolved_from: None,
+            search_match: None,
+        }
     }
     
     async fn parse_brew_info(&self, name: &str, info_output: &str, package_type: PackageType) -> Result<BrewPackage, BrewDeckError> {
@@ -476,7 +2026,9 @@ impl PackageService {
             installed: false,
             outdated: false,
             homepage: String::new(),
+            license: None,
             dependencies: Vec::new(),
+            linux_dependencies: Vec::new(),
             conflicts: Vec::new(),
             caveats: String::new(),
             analytics: PackageAnalytics::default(),
@@ -485,6 +2037,13 @@ impl PackageService {
             install_size: None,
             last_updated: None,
             package_type,
+            tap: None,
+            deprecated: false,
+            auto_updates: false,
+            installation: None,
+            bottle: None,
+            resolved_from: None,
+            search_match: None,
         };
         
         let mut in_caveats = false;
@@ -530,15 +2089,44 @@ impl PackageService {
         }
         
         // Check installation status
-        let installed = self.brew_client.list_installed(package_type).await.unwrap_or_default();
-        let outdated = self.brew_client.list_outdated(package_type).await.unwrap_or_default();
+        let installed = self.installed_packages(package_type).await;
         
-        package.installed = installed.contains(&name.to_string());
-        package.outdated = package.installed && outdated.contains(&name.to_string());
+        package.installation = installed.get(name).cloned();
+        package.installed = package.installation.is_some();
+        package.outdated = package
+            .installation
+            .as_ref()
+            .is_some_and(|i| i.is_outdated(&package.version, package.auto_updates, false));
         
         Ok(package)
     }
     
+    /// Installed state read from the Cellar and Caskroom, falling back to
+    /// `brew list` and `brew outdated` when the prefix can't be read.
+    async fn installed_packages(&self, package_type: PackageType) -> HashMap<String, InstalledPackage> {
+        match self.installed_state.scan(package_type).await {
+            Ok(installed) => return installed,
+            Err(e) => warn!("Failed to read installed {} packages from disk, asking brew: {}", package_type, e),
+        }
+        
+        let installed = self.brew_client.list_installed(package_type).await.unwrap_or_default();
+        let outdated: HashSet<String> = self.brew_client
+            .list_outdated(package_type)
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .collect();
+        
+        installed
+            .into_iter()
+            .map(|name| {
+                let mut package = InstalledPackage::from_name(&name, package_type);
+                package.reported_outdated = Some(outdated.contains(&name));
+                (name, package)
+            })
+            .collect()
+    }
+    
     async fn invalidate_package_caches(&self, name: &str, package_type: PackageType) {
         // Invalidate specific package cache
         let package_key = format!("package_{package_type}_{name}");
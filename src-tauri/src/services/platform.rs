@@ -0,0 +1,274 @@
+use crate::error::BrewDeckError;
+use crate::services::brew_client::PackageType;
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use std::sync::OnceLock;
+use tracing::{debug, warn};
+
+/// macOS releases brew tags bottles for, newest first. From Big Sur on only
+/// the major version matters.
+const MACOS_RELEASES: &[(u32, u32, &str)] = &[
+    (26, 0, "tahoe"),
+    (15, 0, "sequoia"),
+    (14, 0, "sonoma"),
+    (13, 0, "ventura"),
+    (12, 0, "monterey"),
+    (11, 0, "big_sur"),
+    (10, 15, "catalina"),
+    (10, 14, "mojave"),
+    (10, 13, "high_sierra"),
+    (10, 12, "sierra"),
+    (10, 11, "el_capitan"),
+];
+
+/// Bottles tagged `all` are architecture and OS independent
+pub const UNIVERSAL_BOTTLE_TAG: &str = "all";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HostOs {
+    MacOs,
+    Linux,
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HostArch {
+    Arm64,
+    X86_64,
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct MacOsVersion {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl MacOsVersion {
+    /// Parses `14.5`, `10.15.7` or `26`
+    pub fn parse(version: &str) -> Option<Self> {
+        let mut parts = version.trim().split('.');
+        let major = parts.next()?.parse().ok()?;
+        let minor = parts.next().map_or(Some(0), |minor| minor.parse().ok())?;
+
+        Some(Self { major, minor })
+    }
+
+    /// Parses a version or a release codename such as `big_sur`
+    pub fn parse_requirement(value: &str) -> Option<Self> {
+        let value = value.trim();
+
+        MACOS_RELEASES
+            .iter()
+            .find(|(_, _, codename)| codename.eq_ignore_ascii_case(value))
+            .map(|&(major, minor, _)| Self { major, minor })
+            .or_else(|| Self::parse(value))
+    }
+
+    /// The release this version belongs to, e.g. 14.5 and 14.0 are both
+    /// Sonoma; requirements compare releases rather than point versions
+    pub fn release(self) -> (u32, u32) {
+        if self.major >= 11 {
+            (self.major, 0)
+        } else {
+            (self.major, self.minor)
+        }
+    }
+
+    /// Bottle tag name of the release, e.g. `sonoma`; unknown newer releases
+    /// map to the newest known one
+    pub fn codename(self) -> Option<&'static str> {
+        let release = self.release();
+
+        MACOS_RELEASES
+            .iter()
+            .find(|(major, minor, _)| (*major, *minor) <= release)
+            .map(|(_, _, codename)| *codename)
+    }
+}
+
+impl std::fmt::Display for MacOsVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+/// What BrewDeck can offer on this host, for the UI to hide what does not apply
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlatformCapabilities {
+    pub platform: HostPlatform,
+    pub bottle_tag: Option<String>,
+    pub package_types: Vec<PackageType>,
+    pub casks: bool,
+    /// Formulae may pull in extra dependencies that macOS would provide
+    pub installs_uses_from_macos: bool,
+}
+
+/// The machine BrewDeck runs on, as far as picking bottles and
+/// packages goes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostPlatform {
+    pub os: HostOs,
+    pub arch: HostArch,
+    pub macos_version: Option<MacOsVersion>,
+}
+
+impl HostPlatform {
+    /// A specific platform, e.g. to evaluate requirements for another machine
+    pub fn new(os: HostOs, arch: HostArch, macos_version: Option<MacOsVersion>) -> Self {
+        Self { os, arch, macos_version }
+    }
+
+    /// Detected once per process; the OS does not change under us
+    pub fn current() -> &'static HostPlatform {
+        static CURRENT: OnceLock<HostPlatform> = OnceLock::new();
+        CURRENT.get_or_init(Self::detect)
+    }
+
+    fn detect() -> Self {
+        let os = match std::env::consts::OS {
+            "macos" => HostOs::MacOs,
+            "linux" => HostOs::Linux,
+            _ => HostOs::Other,
+        };
+        let arch = match std::env::consts::ARCH {
+            "aarch64" => HostArch::Arm64,
+            "x86_64" => HostArch::X86_64,
+            _ => HostArch::Other,
+        };
+        let macos_version = match os {
+            HostOs::MacOs => detect_macos_version(),
+            _ => None,
+        };
+
+        let platform = Self::new(os, arch, macos_version);
+        debug!("Host platform: {:?} (bottle tag {:?})", platform, platform.bottle_tag());
+        platform
+    }
+
+    pub fn is_macos(&self) -> bool {
+        self.os == HostOs::MacOs
+    }
+
+    pub fn is_linux(&self) -> bool {
+        self.os == HostOs::Linux
+    }
+
+    /// Casks are macOS apps; Homebrew on Linux only installs formulae
+    pub fn supports(&self, package_type: PackageType) -> bool {
+        match package_type {
+            PackageType::Formula => true,
+            PackageType::Cask => self.is_macos(),
+        }
+    }
+
+    pub fn supported_package_types(&self) -> Vec<PackageType> {
+        [PackageType::Formula, PackageType::Cask]
+            .into_iter()
+            .filter(|package_type| self.supports(*package_type))
+            .collect()
+    }
+
+    pub fn ensure_supported(&self, package_type: PackageType) -> Result<(), BrewDeckError> {
+        if self.supports(package_type) {
+            return Ok(());
+        }
+
+        Err(BrewDeckError::UnsupportedPlatform(format!(
+            "{package_type} packages are only available on macOS"
+        )))
+    }
+
+    /// formulae.brew.sh publishes Linux installs separately
+    pub fn analytics_root(&self) -> &'static str {
+        if self.is_linux() {
+            "analytics-linux"
+        } else {
+            "analytics"
+        }
+    }
+
+    /// `uses_from_macos` dependencies ship with macOS but are installed from
+    /// Homebrew everywhere else
+    pub fn installs_uses_from_macos(&self) -> bool {
+        !self.is_macos()
+    }
+
+    pub fn capabilities(&self) -> PlatformCapabilities {
+        PlatformCapabilities {
+            platform: self.clone(),
+            bottle_tag: self.bottle_tag(),
+            package_types: self.supported_package_types(),
+            casks: self.supports(PackageType::Cask),
+            installs_uses_from_macos: self.installs_uses_from_macos(),
+        }
+    }
+
+    /// Tag of bottles built for exactly this host, e.g. `arm64_sonoma`,
+    /// `ventura` or `x86_64_linux`
+    pub fn bottle_tag(&self) -> Option<String> {
+        match self.os {
+            HostOs::MacOs => {
+                let codename = self.macos_version?.codename()?;
+                Some(self.macos_tag(codename))
+            }
+            HostOs::Linux => match self.arch {
+                HostArch::Arm64 => Some("arm64_linux".to_string()),
+                HostArch::X86_64 => Some("x86_64_linux".to_string()),
+                HostArch::Other => None,
+            },
+            HostOs::Other => None,
+        }
+    }
+
+    /// Tags brew will pour on this host, in order of preference: the host's
+    /// own tag, bottles built for older macOS releases on the same
+    /// architecture, then `all`.
+    pub fn compatible_bottle_tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = self.bottle_tag().into_iter().collect();
+
+        if let Some(version) = self.macos_version.filter(|_| self.is_macos()) {
+            let release = version.release();
+            tags.extend(
+                MACOS_RELEASES
+                    .iter()
+                    .filter(|(major, minor, _)| (*major, *minor) < release)
+                    .map(|(_, _, codename)| self.macos_tag(codename)),
+            );
+            tags.dedup();
+        }
+
+        // Older Linux ARM bottles were tagged after the kernel's arch name
+        if self.is_linux() && self.arch == HostArch::Arm64 {
+            tags.push("aarch64_linux".to_string());
+        }
+
+        tags.push(UNIVERSAL_BOTTLE_TAG.to_string());
+        tags
+    }
+
+    fn macos_tag(&self, codename: &str) -> String {
+        match self.arch {
+            HostArch::Arm64 => format!("arm64_{codename}"),
+            _ => codename.to_string(),
+        }
+    }
+}
+
+fn detect_macos_version() -> Option<MacOsVersion> {
+    let output = match Command::new("sw_vers").arg("-productVersion").output() {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => {
+            warn!("sw_vers failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+            return None;
+        }
+        Err(e) => {
+            warn!("Failed to run sw_vers: {}", e);
+            return None;
+        }
+    };
+
+    MacOsVersion::parse(&String::from_utf8_lossy(&output.stdout))
+}
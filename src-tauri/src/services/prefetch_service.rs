@@ -1,6 +1,7 @@
 use crate::error::BrewDeckError;
 use crate::services::{CacheManager, PackageService};
 use crate::services::brew_client::PackageType;
+use crate::services::platform::HostPlatform;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -168,6 +169,12 @@ impl PrefetchService {
             }
         }
 
+        // Leave the shared API rate budget to interactive requests
+        if !self.package_service.rate_limiter().has_headroom().await {
+            debug!("Skipping prefetch, API rate budget is low");
+            return false;
+        }
+
         // Check if we have capacity
         self.request_semaphore.available_permits() > 0
     }
@@ -200,6 +207,11 @@ impl PrefetchService {
 
             // Prefetch top 10 popular packages
             for package_name in popular_packages.iter().take(10) {
+                if !self.package_service.rate_limiter().has_headroom().await {
+                    debug!("Stopping popular package prefetch, API rate budget is low");
+                    break;
+                }
+                
                 if let Err(e) = self.package_service.get_package_details(package_name, package_type).await {
                     warn!("Failed to prefetch popular package {}: {}", package_name, e);
                 }
@@ -239,6 +251,10 @@ impl PrefetchService {
             
             // Prefetch dependencies (limited to avoid overwhelming)
             for dep in package.dependencies.iter().take(3) {
+                if !self.package_service.rate_limiter().has_headroom().await {
+                    break;
+                }
+                
                 if let Err(e) = self.package_service.get_package_details(dep, package_type).await {
                     debug!("Failed to prefetch dependency {}: {}", dep, e);
                 }
@@ -266,7 +282,7 @@ impl PrefetchService {
         }
 
         // Check both package types for stale data
-        for package_type in [PackageType::Formula, PackageType::Cask] {
+        for package_type in HostPlatform::current().supported_package_types() {
             let cache_key = format!("packages_{}", package_type);
             
             // Check if cache is stale (older than 5 minutes)
@@ -296,10 +312,14 @@ impl PrefetchService {
 
         // Simple predictive prefetching based on user patterns
         for pattern in user_patterns.iter().take(5) {
-            for package_type in [PackageType::Formula, PackageType::Cask] {
+            for package_type in HostPlatform::current().supported_package_types() {
                 if let Ok(search_results) = self.package_service.search_packages(pattern, package_type).await {
                     // Prefetch top 2 search results
                     for package in search_results.packages.iter().take(2) {
+                        if !self.package_service.rate_limiter().has_headroom().await {
+                            return Ok(());
+                        }
+                        
                         if let Err(e) = self.package_service.get_package_details(&package.name, package_type).await {
                             debug!("Failed to predictively prefetch {}: {}", package.name, e);
                         }
@@ -325,12 +345,9 @@ impl PrefetchService {
         let config = self.config.read().await;
         
         let mut popular: Vec<String> = packages
-            .into_iter()
+            .iter()
             .filter(|pkg| pkg.analytics.downloads_365d > config.popularity_threshold)
-            .map(|pkg| (pkg.name, pkg.analytics.downloads_365d))
-            .collect::<Vec<_>>()
-            .into_iter()
-            .map(|(name, _)| name)
+            .map(|pkg| pkg.name.clone())
             .collect();
 
         popular.sort();
@@ -392,7 +409,7 @@ impl PrefetchService {
             loop {
                 interval.tick().await;
                 
-                for package_type in [PackageType::Formula, PackageType::Cask] {
+                for package_type in HostPlatform::current().supported_package_types() {
                     if let Err(e) = popular_service.prefetch_popular_packages(package_type).await {
                         warn!("Popular packages prefetch failed for {}: {}", package_type, e);
                     }
@@ -0,0 +1,572 @@
+use crate::error::BrewDeckError;
+use crate::services::brew_client::PackageType;
+use crate::services::package_query::mentions_license;
+use crate::services::package_service::BrewPackage;
+use chrono::NaiveDate;
+
+/// Fields a `field:value` term can name
+const FIELDS: &[&str] = &[
+    "name", "desc", "description", "tap", "license", "type", "installed", "outdated", "deprecated",
+    "deps", "downloads", "size", "installed_at",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Equal,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl Comparison {
+    fn holds<T: PartialOrd>(self, actual: T, expected: T) -> bool {
+        match self {
+            Comparison::Equal => actual == expected,
+            Comparison::Less => actual < expected,
+            Comparison::LessOrEqual => actual <= expected,
+            Comparison::Greater => actual > expected,
+            Comparison::GreaterOrEqual => actual >= expected,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    /// A bare word or phrase, found in the name or description
+    Text(String),
+    Name(String),
+    Description(String),
+    Tap(String),
+    License(String),
+    Type(PackageType),
+    Installed(bool),
+    Outdated(bool),
+    Deprecated(bool),
+    Dependencies(Comparison, u64),
+    Downloads(Comparison, u64),
+    /// Installed size in bytes
+    Size(Comparison, u64),
+    InstalledAt(Comparison, NaiveDate),
+}
+
+/// A parsed search query such as
+/// `installed:true tap:homebrew/core license:MIT deps:<5 "language server"`.
+///
+/// Terms next to each other must all match; `OR` between them needs either
+/// and binds looser, so `a b OR c` is `(a b) OR c`. `-` or `NOT` negates a
+/// term or a parenthesized group. Bare words and quoted phrases match the
+/// name or description.
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryExpression {
+    Condition(Condition),
+    Not(Box<QueryExpression>),
+    And(Vec<QueryExpression>),
+    Or(Vec<QueryExpression>),
+}
+
+impl QueryExpression {
+    pub fn parse(query: &str) -> Result<Self, BrewDeckError> {
+        let tokens = lex(query)?;
+        let mut parser = Parser {
+            tokens: &tokens,
+            position: 0,
+            end: query.chars().count(),
+        };
+
+        if tokens.is_empty() {
+            return Err(syntax_error("Query is empty", 0));
+        }
+
+        let expression = parser.or()?;
+        match parser.peek() {
+            None => Ok(expression),
+            Some(token) if token.kind == TokenKind::CloseParen => {
+                Err(syntax_error("Unmatched ')'", token.position))
+            }
+            Some(token) => Err(syntax_error("Unexpected input", token.position)),
+        }
+    }
+
+    pub fn matches(&self, package: &BrewPackage) -> bool {
+        match self {
+            QueryExpression::Condition(condition) => condition.matches(package),
+            QueryExpression::Not(inner) => !inner.matches(package),
+            QueryExpression::And(terms) => terms.iter().all(|term| term.matches(package)),
+            QueryExpression::Or(terms) => terms.iter().any(|term| term.matches(package)),
+        }
+    }
+}
+
+impl Condition {
+    fn matches(&self, package: &BrewPackage) -> bool {
+        let contains = |haystack: &str, needle: &str| haystack.to_lowercase().contains(needle);
+
+        match self {
+            Condition::Text(text) => contains(&package.name, text) || contains(&package.description, text),
+            Condition::Name(text) => contains(&package.name, text),
+            Condition::Description(text) => contains(&package.description, text),
+            Condition::Tap(tap) => package.tap.as_deref().is_some_and(|package_tap| package_tap.eq_ignore_ascii_case(tap)),
+            Condition::License(license) => mentions_license(package, license),
+            Condition::Type(package_type) => package.package_type == *package_type,
+            Condition::Installed(installed) => package.installed == *installed,
+            Condition::Outdated(outdated) => package.outdated == *outdated,
+            Condition::Deprecated(deprecated) => package.deprecated == *deprecated,
+            Condition::Dependencies(comparison, count) => comparison.holds(package.dependencies.len() as u64, *count),
+            Condition::Downloads(comparison, count) => comparison.holds(package.analytics.downloads_365d, *count),
+            Condition::Size(comparison, bytes) => package
+                .install_size
+                .is_some_and(|size| comparison.holds(size, *bytes)),
+            Condition::InstalledAt(comparison, date) => package
+                .installation
+                .as_ref()
+                .and_then(|installation| installation.installed_time)
+                .is_some_and(|time| comparison.holds(time.date_naive(), *date)),
+        }
+    }
+}
+
+fn syntax_error(message: impl Into<String>, position: usize) -> BrewDeckError {
+    BrewDeckError::QuerySyntaxError(message.into(), position)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    OpenParen,
+    CloseParen,
+    Not,
+    And,
+    Or,
+    Term {
+        field: Option<String>,
+        comparison: Option<Comparison>,
+        value: String,
+        /// Character offset of the value, for errors about it
+        value_position: usize,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    /// Character offset of the token's first character
+    position: usize,
+}
+
+fn lex(query: &str) -> Result<Vec<Token>, BrewDeckError> {
+    let chars: Vec<char> = query.chars().collect();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+
+    while index < chars.len() {
+        let start = index;
+
+        match chars[index] {
+            c if c.is_whitespace() => index += 1,
+            '(' => {
+                tokens.push(Token { kind: TokenKind::OpenParen, position: start });
+                index += 1;
+            }
+            ')' => {
+                tokens.push(Token { kind: TokenKind::CloseParen, position: start });
+                index += 1;
+            }
+            // A leading `-` negates; inside a word it is part of it, e.g. `git-lfs`
+            '-' if chars.get(index + 1).is_some_and(|next| !next.is_whitespace()) => {
+                tokens.push(Token { kind: TokenKind::Not, position: start });
+                index += 1;
+            }
+            '"' => {
+                let (phrase, next) = read_phrase(&chars, index)?;
+                tokens.push(Token {
+                    kind: TokenKind::Term {
+                        field: None,
+                        comparison: None,
+                        value: phrase,
+                        value_position: start,
+                    },
+                    position: start,
+                });
+                index = next;
+            }
+            _ => {
+                let (token, next) = read_word(&chars, index)?;
+                tokens.push(token);
+                index = next;
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Reads a quoted phrase starting at the opening quote; returns it and the
+/// index after the closing quote
+fn read_phrase(chars: &[char], start: usize) -> Result<(String, usize), BrewDeckError> {
+    let close = chars[start + 1..]
+        .iter()
+        .position(|c| *c == '"')
+        .ok_or_else(|| syntax_error("Unterminated quote", start))?;
+    let end = start + 1 + close;
+
+    Ok((chars[start + 1..end].iter().collect(), end + 1))
+}
+
+fn read_word(chars: &[char], start: usize) -> Result<(Token, usize), BrewDeckError> {
+    let is_word_end = |c: char| c.is_whitespace() || c == '(' || c == ')' || c == '"';
+    let mut index = start;
+    while index < chars.len() && !is_word_end(chars[index]) && chars[index] != ':' {
+        index += 1;
+    }
+    let word: String = chars[start..index].iter().collect();
+
+    if chars.get(index) != Some(&':') {
+        let kind = match word.as_str() {
+            "AND" => TokenKind::And,
+            "OR" => TokenKind::Or,
+            "NOT" => TokenKind::Not,
+            _ => TokenKind::Term {
+                field: None,
+                comparison: None,
+                value: word,
+                value_position: start,
+            },
+        };
+        return Ok((Token { kind, position: start }, index));
+    }
+
+    let field = word.to_lowercase();
+    if !FIELDS.contains(&field.as_str()) {
+        return Err(syntax_error(
+            format!("Unknown field '{word}'; expected one of {}", FIELDS.join(", ")),
+            start,
+        ));
+    }
+    index += 1;
+
+    let comparison = [
+        ("<=", Comparison::LessOrEqual),
+        (">=", Comparison::GreaterOrEqual),
+        ("<", Comparison::Less),
+        (">", Comparison::Greater),
+        ("=", Comparison::Equal),
+    ]
+    .into_iter()
+    .find(|(operator, _)| operator.chars().enumerate().all(|(offset, c)| chars.get(index + offset) == Some(&c)))
+    .map(|(operator, comparison)| {
+        index += operator.len();
+        comparison
+    });
+
+    let value_position = index;
+    let (value, next) = if chars.get(index) == Some(&'"') {
+        read_phrase(chars, index)?
+    } else {
+        let mut end = index;
+        while end < chars.len() && !is_word_end(chars[end]) {
+            end += 1;
+        }
+        (chars[index..end].iter().collect(), end)
+    };
+
+    if value.is_empty() {
+        return Err(syntax_error(format!("Missing value after '{word}:'"), value_position));
+    }
+
+    let token = Token {
+        kind: TokenKind::Term {
+            field: Some(field),
+            comparison,
+            value,
+            value_position,
+        },
+        position: start,
+    };
+    Ok((token, next))
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+    /// Length of the query, where errors about missing input point
+    end: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.position);
+        self.position += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<QueryExpression, BrewDeckError> {
+        let mut terms = vec![self.and()?];
+
+        while self.peek().is_some_and(|token| token.kind == TokenKind::Or) {
+            self.next();
+            terms.push(self.and()?);
+        }
+
+        Ok(if terms.len() == 1 { terms.remove(0) } else { QueryExpression::Or(terms) })
+    }
+
+    /// `AND` is optional; terms next to each other are joined by it
+    fn and(&mut self) -> Result<QueryExpression, BrewDeckError> {
+        let mut terms = vec![self.unary()?];
+
+        loop {
+            match self.peek().map(|token| &token.kind) {
+                Some(TokenKind::And) => {
+                    self.next();
+                    terms.push(self.unary()?);
+                }
+                Some(TokenKind::Or | TokenKind::CloseParen) | None => break,
+                Some(_) => terms.push(self.unary()?),
+            }
+        }
+
+        Ok(if terms.len() == 1 { terms.remove(0) } else { QueryExpression::And(terms) })
+    }
+
+    fn unary(&mut self) -> Result<QueryExpression, BrewDeckError> {
+        if self.peek().is_some_and(|token| token.kind == TokenKind::Not) {
+            self.next();
+            return Ok(QueryExpression::Not(Box::new(self.unary()?)));
+        }
+
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<QueryExpression, BrewDeckError> {
+        let Some(token) = self.next() else {
+            return Err(syntax_error("Expected a search term", self.end));
+        };
+
+        match &token.kind {
+            TokenKind::OpenParen => {
+                let inner = self.or()?;
+                match self.next() {
+                    Some(Token { kind: TokenKind::CloseParen, .. }) => Ok(inner),
+                    _ => Err(syntax_error("Missing ')' for this '('", token.position)),
+                }
+            }
+            TokenKind::Term { field, comparison, value, value_position } => {
+                condition(field.as_deref(), *comparison, value, *value_position).map(QueryExpression::Condition)
+            }
+            TokenKind::CloseParen => Err(syntax_error("Unmatched ')'", token.position)),
+            TokenKind::And | TokenKind::Or | TokenKind::Not => {
+                Err(syntax_error("Expected a search term", token.position))
+            }
+        }
+    }
+}
+
+fn condition(
+    field: Option<&str>,
+    comparison: Option<Comparison>,
+    value: &str,
+    position: usize,
+) -> Result<Condition, BrewDeckError> {
+    let Some(field) = field else {
+        return Ok(Condition::Text(value.to_lowercase()));
+    };
+
+    let compared = |kind: &str| -> Result<(), BrewDeckError> {
+        match comparison {
+            Some(comparison) if comparison != Comparison::Equal => Err(syntax_error(
+                format!("'{field}' is {kind} and cannot be compared with < or >"),
+                position,
+            )),
+            _ => Ok(()),
+        }
+    };
+    let comparison_or_equal = comparison.unwrap_or(Comparison::Equal);
+
+    let condition = match field {
+        "name" | "desc" | "description" | "tap" | "license" => {
+            compared("text")?;
+            match field {
+                "name" => Condition::Name(value.to_lowercase()),
+                "tap" => Condition::Tap(value.to_string()),
+                "license" => Condition::License(value.to_string()),
+                _ => Condition::Description(value.to_lowercase()),
+            }
+        }
+        "type" => {
+            compared("a package type")?;
+            let package_type = value
+                .parse::<PackageType>()
+                .map_err(|_| syntax_error(format!("Expected formula or cask, got '{value}'"), position))?;
+            Condition::Type(package_type)
+        }
+        "installed" | "outdated" | "deprecated" => {
+            compared("true or false")?;
+            let flag = parse_bool(value)
+                .ok_or_else(|| syntax_error(format!("Expected true or false, got '{value}'"), position))?;
+            match field {
+                "installed" => Condition::Installed(flag),
+                "outdated" => Condition::Outdated(flag),
+                _ => Condition::Deprecated(flag),
+            }
+        }
+        "deps" | "downloads" => {
+            let count = value
+                .parse::<u64>()
+                .map_err(|_| syntax_error(format!("Expected a whole number, got '{value}'"), position))?;
+            match field {
+                "deps" => Condition::Dependencies(comparison_or_equal, count),
+                _ => Condition::Downloads(comparison_or_equal, count),
+            }
+        }
+        "size" => {
+            let bytes = parse_size(value).ok_or_else(|| {
+                syntax_error(format!("Expected a size such as 500KB or 1.5GB, got '{value}'"), position)
+            })?;
+            Condition::Size(comparison_or_equal, bytes)
+        }
+        _ => {
+            let date = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .map_err(|_| syntax_error(format!("Expected a date as YYYY-MM-DD, got '{value}'"), position))?;
+            Condition::InstalledAt(comparison_or_equal, date)
+        }
+    };
+
+    Ok(condition)
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "true" | "yes" => Some(true),
+        "false" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+/// Bytes, or a number with a `B`, `KB`, `MB` or `GB` suffix in powers of 1024
+fn parse_size(value: &str) -> Option<u64> {
+    let upper = value.to_uppercase();
+    let split = upper.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(upper.len());
+    let (number, unit) = upper.split_at(split);
+
+    let multiplier: u64 = match unit {
+        "" | "B" => 1,
+        "K" | "KB" => 1024,
+        "M" | "MB" => 1024 * 1024,
+        "G" | "GB" => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+    let number: f64 = number.parse().ok()?;
+
+    Some((number * multiplier as f64) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(value: &str) -> QueryExpression {
+        QueryExpression::Condition(Condition::Text(value.to_string()))
+    }
+
+    fn not(expression: QueryExpression) -> QueryExpression {
+        QueryExpression::Not(Box::new(expression))
+    }
+
+    /// The message and position of the query's syntax error
+    fn syntax_error_of(query: &str) -> (String, usize) {
+        match QueryExpression::parse(query) {
+            Err(BrewDeckError::QuerySyntaxError(message, position)) => (message, position),
+            other => panic!("expected a syntax error for {query:?}, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn lexes_character_positions() {
+        let tokens = lex("name:\"git lfs\" (-wget)").unwrap();
+        let positions: Vec<usize> = tokens.iter().map(|token| token.position).collect();
+
+        assert_eq!(positions, vec![0, 15, 16, 17, 21]);
+        assert!(matches!(
+            &tokens[0].kind,
+            TokenKind::Term { field: Some(field), value, value_position: 5, .. } if field == "name" && value == "git lfs"
+        ));
+        assert_eq!(tokens[2].kind, TokenKind::Not);
+    }
+
+    #[test]
+    fn positions_count_characters_not_bytes() {
+        assert_eq!(syntax_error_of("café \"crème"), ("Unterminated quote".to_string(), 5));
+    }
+
+    #[test]
+    fn negates_with_dash_or_not() {
+        assert_eq!(QueryExpression::parse("-wget").unwrap(), not(text("wget")));
+        assert_eq!(QueryExpression::parse("NOT wget").unwrap(), not(text("wget")));
+        assert_eq!(
+            QueryExpression::parse("-(wget OR curl)").unwrap(),
+            not(QueryExpression::Or(vec![text("wget"), text("curl")]))
+        );
+
+        // Inside a word `-` is part of it
+        assert_eq!(QueryExpression::parse("git-lfs").unwrap(), text("git-lfs"));
+    }
+
+    #[test]
+    fn or_binds_looser_than_and() {
+        assert_eq!(
+            QueryExpression::parse("a b OR c").unwrap(),
+            QueryExpression::Or(vec![QueryExpression::And(vec![text("a"), text("b")]), text("c")])
+        );
+        assert_eq!(
+            QueryExpression::parse("a AND (b OR c)").unwrap(),
+            QueryExpression::And(vec![text("a"), QueryExpression::Or(vec![text("b"), text("c")])])
+        );
+    }
+
+    #[test]
+    fn parses_installed_dates() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+
+        assert_eq!(
+            QueryExpression::parse("installed_at:>=2024-01-31").unwrap(),
+            QueryExpression::Condition(Condition::InstalledAt(Comparison::GreaterOrEqual, date))
+        );
+        assert_eq!(
+            QueryExpression::parse("installed_at:2024-01-31").unwrap(),
+            QueryExpression::Condition(Condition::InstalledAt(Comparison::Equal, date))
+        );
+
+        let (message, position) = syntax_error_of("wget installed_at:<2024-13-01");
+        assert_eq!(message, "Expected a date as YYYY-MM-DD, got '2024-13-01'");
+        assert_eq!(position, 19);
+    }
+
+    #[test]
+    fn reports_unterminated_quote_at_opening_quote() {
+        assert_eq!(syntax_error_of("wget \"language server"), ("Unterminated quote".to_string(), 5));
+        assert_eq!(syntax_error_of("desc:\"language").1, 5);
+    }
+
+    #[test]
+    fn reports_unknown_field_at_its_start() {
+        let (message, position) = syntax_error_of("wget colour:red");
+
+        assert!(message.starts_with("Unknown field 'colour'"), "{message}");
+        assert_eq!(position, 5);
+    }
+
+    #[test]
+    fn reports_unbalanced_parentheses() {
+        assert_eq!(syntax_error_of("(wget OR curl"), ("Missing ')' for this '('".to_string(), 0));
+        assert_eq!(syntax_error_of("wget)"), ("Unmatched ')'".to_string(), 4));
+        assert_eq!(syntax_error_of("wget OR"), ("Expected a search term".to_string(), 7));
+
+        // Commands hand errors to the UI as their message
+        let error = QueryExpression::parse("wget)").unwrap_err();
+        assert_eq!(error.to_string(), "Invalid query at position 4: Unmatched ')'");
+    }
+}
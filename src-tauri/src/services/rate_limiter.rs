@@ -0,0 +1,190 @@
+use crate::error::MAX_RETRY_AFTER;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tracing::{debug, warn};
+
+/// Enough for a full analytics refresh (nine requests) in one burst
+const DEFAULT_BURST: u32 = 10;
+const DEFAULT_REQUESTS_PER_SECOND: f64 = 4.0;
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+    /// Host -> end of the pause its `Retry-After` asked for; nothing is sent
+    /// to the host before then
+    paused_until: HashMap<String, Instant>,
+}
+
+impl Bucket {
+    fn refill(&mut self, now: Instant, capacity: f64, refill_per_second: f64) {
+        let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * refill_per_second).min(capacity);
+        self.refilled_at = now;
+    }
+
+    fn paused_for(&mut self, host: &str, now: Instant) -> Option<Duration> {
+        self.paused_until.retain(|_, until| *until > now);
+        self.paused_until.get(host).map(|until| *until - now)
+    }
+}
+
+/// Token bucket shared by every request to the Homebrew API, so foreground
+/// lookups, analytics refreshes and background prefetching together stay
+/// under a polite request rate.
+pub struct RateLimiter {
+    capacity: f64,
+    refill_per_second: f64,
+    bucket: Mutex<Bucket>,
+}
+
+impl RateLimiter {
+    pub fn new(burst: u32, requests_per_second: f64) -> Self {
+        let capacity = f64::from(burst.max(1));
+
+        Self {
+            capacity,
+            refill_per_second: requests_per_second.max(0.1),
+            bucket: Mutex::new(Bucket {
+                tokens: capacity,
+                refilled_at: Instant::now(),
+                paused_until: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Waits for a token, or for a pause requested by `url`'s host to end
+    pub async fn acquire(&self, url: &str) {
+        let host = host_of(url);
+
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().await;
+                let now = Instant::now();
+
+                match bucket.paused_for(&host, now) {
+                    Some(pause) => pause,
+                    None => {
+                        bucket.refill(now, self.capacity, self.refill_per_second);
+                        if bucket.tokens >= 1.0 {
+                            bucket.tokens -= 1.0;
+                            return;
+                        }
+                        Duration::from_secs_f64((1.0 - bucket.tokens) / self.refill_per_second)
+                    }
+                }
+            };
+
+            debug!("Rate limited, waiting {:?} for a request slot", wait);
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Whether there is room for optional background work: no host has asked
+    /// us to back off and at least half the bucket is free, so prefetching
+    /// never makes interactive requests wait. Takes no token; the requests
+    /// themselves still go through `acquire`.
+    pub async fn has_headroom(&self) -> bool {
+        let mut bucket = self.bucket.lock().await;
+        let now = Instant::now();
+
+        bucket.paused_until.retain(|_, until| *until > now);
+        if !bucket.paused_until.is_empty() {
+            return false;
+        }
+
+        bucket.refill(now, self.capacity, self.refill_per_second);
+        bucket.tokens >= self.capacity / 2.0
+    }
+
+    /// Stops requests to `url`'s host for `duration` after it answered 429.
+    /// Capped at `MAX_RETRY_AFTER`, so a bogus `Retry-After` cannot stall the
+    /// app; longer waits are left to the callers, which give up instead.
+    pub async fn pause(&self, url: &str, duration: Duration) {
+        let host = host_of(url);
+        let duration = duration.min(MAX_RETRY_AFTER);
+        let mut bucket = self.bucket.lock().await;
+        let until = Instant::now() + duration;
+
+        let current = bucket.paused_until.get(&host).copied();
+        if current.is_none_or(|current| current < until) {
+            warn!("{} asked us to back off, pausing its requests for {:?}", host, duration);
+            bucket.paused_until.insert(host, until);
+        }
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(DEFAULT_BURST, DEFAULT_REQUESTS_PER_SECOND)
+    }
+}
+
+/// Pauses are kept per host; a URL that does not parse is its own key
+fn host_of(url: &str) -> String {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_else(|| url.to_string())
+}
+
+/// Parses a `Retry-After` header, either delay-seconds or an HTTP date
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    let delay = date.with_timezone(&Utc) - Utc::now();
+    Some(delay.to_std().unwrap_or(Duration::ZERO))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const API_URL: &str = "https://formulae.brew.sh/api/formula.json";
+    const MIRROR_URL: &str = "https://mirror.example.com/api/formula.json";
+
+    #[tokio::test]
+    async fn pause_is_capped() {
+        let limiter = RateLimiter::default();
+        limiter.pause(API_URL, Duration::from_secs(86_400)).await;
+
+        let mut bucket = limiter.bucket.lock().await;
+        let paused_for = bucket.paused_for("formulae.brew.sh", Instant::now()).unwrap();
+        assert!(paused_for <= MAX_RETRY_AFTER);
+    }
+
+    #[tokio::test]
+    async fn pause_only_holds_back_its_host() {
+        let limiter = RateLimiter::default();
+        limiter.pause(API_URL, Duration::from_secs(30)).await;
+
+        let mirror = tokio::time::timeout(Duration::from_millis(100), limiter.acquire(MIRROR_URL)).await;
+        assert!(mirror.is_ok());
+
+        let api = tokio::time::timeout(Duration::from_millis(100), limiter.acquire(API_URL)).await;
+        assert!(api.is_err());
+    }
+
+    #[tokio::test]
+    async fn no_headroom_while_paused() {
+        let limiter = RateLimiter::default();
+        assert!(limiter.has_headroom().await);
+
+        limiter.pause(API_URL, Duration::from_secs(30)).await;
+        assert!(!limiter.has_headroom().await);
+    }
+
+    #[test]
+    fn parses_retry_after() {
+        assert_eq!(parse_retry_after(" 120 "), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+}
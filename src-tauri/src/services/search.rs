@@ -0,0 +1,524 @@
+use crate::services::api_client::ApiPackageInfo;
+use crate::services::catalog_index::canonical_name;
+use serde::{Deserialize, Serialize};
+
+/// Points for matching the package name; other name-like fields are scaled
+/// down from these by their weight
+const EXACT_SCORE: f64 = 1000.0;
+const PREFIX_SCORE: f64 = 700.0;
+const TOKEN_SCORE: f64 = 600.0;
+const TOKEN_PREFIX_SCORE: f64 = 550.0;
+const SUBSTRING_SCORE: f64 = 450.0;
+
+/// Description matches stay below any name substring match
+const DESCRIPTION_WORD_SCORE: f64 = 120.0;
+const DESCRIPTION_WORD_PREFIX_SCORE: f64 = 80.0;
+const DESCRIPTION_ALL_TOKENS_BONUS: f64 = 60.0;
+const DESCRIPTION_MAX_SCORE: f64 = 400.0;
+
+/// A query word matching the tap or homepage domain, e.g. `fonts` or `github`
+const METADATA_WORD_SCORE: f64 = 100.0;
+
+/// Owner of every official tap; matching it says nothing about a package
+pub const OFFICIAL_TAP_OWNER: &str = "homebrew";
+
+/// A one-typo match scores 300, a two-typo match 200
+const FUZZY_SCORE: f64 = 400.0;
+const FUZZY_PENALTY_PER_EDIT: f64 = 100.0;
+
+/// Added for the most installed package, scaled down by popularity percentile
+const POPULARITY_BOOST: f64 = 40.0;
+
+/// Extra characters beyond the query cost this much on prefix matches, so
+/// `git` ranks `git-lfs` above `git-filter-repo`
+const PREFIX_LENGTH_PENALTY: f64 = 2.0;
+const MAX_PREFIX_LENGTH_PENALTY: f64 = 60.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchField {
+    Name,
+    /// A cask's application name, e.g. `Visual Studio Code`
+    DisplayName,
+    Alias,
+    OldName,
+    Description,
+    Tap,
+    /// The homepage's domain, e.g. `git-scm.com`
+    Homepage,
+}
+
+impl SearchField {
+    fn weight(self) -> f64 {
+        match self {
+            SearchField::Name => 1.0,
+            SearchField::Alias => 0.9,
+            SearchField::DisplayName => 0.85,
+            SearchField::OldName => 0.8,
+            SearchField::Description | SearchField::Tap | SearchField::Homepage => 1.0,
+        }
+    }
+}
+
+/// How the best scoring field matched, strongest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchKind {
+    Exact,
+    Prefix,
+    Token,
+    Substring,
+    Description,
+    Fuzzy,
+    /// Only the tap or homepage matched
+    Metadata,
+}
+
+/// A matched span for the UI to highlight
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchHighlight {
+    pub field: SearchField,
+    /// The alias, old name, tap or homepage domain that matched; the name
+    /// and description are on the package itself
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    /// Offset in characters into the field
+    pub start: usize,
+    /// Length in characters
+    pub length: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchMatch {
+    pub score: f64,
+    pub kind: MatchKind,
+    pub matched_fields: Vec<SearchField>,
+    pub highlights: Vec<SearchHighlight>,
+}
+
+/// A search string prepared once for scoring a whole catalog
+pub struct SearchQuery {
+    lower: String,
+    tokens: Vec<String>,
+}
+
+impl SearchQuery {
+    /// `None` for a blank query
+    pub fn new(query: &str) -> Option<Self> {
+        let lower = query.trim().to_lowercase();
+        if lower.is_empty() {
+            return None;
+        }
+
+        let tokens = words(&lower).map(|(_, word)| word.to_string()).collect();
+        Some(Self { lower, tokens })
+    }
+
+    /// The trimmed, lowercased query
+    pub fn text(&self) -> &str {
+        &self.lower
+    }
+
+    /// The query's words
+    pub fn tokens(&self) -> &[String] {
+        &self.tokens
+    }
+
+    /// Whether `term` is within the typos the query allows, as a name match
+    pub fn is_fuzzy_match(&self, term: &str) -> bool {
+        let query_length = self.lower.chars().count();
+        let Some(max_edits) = max_edits(query_length) else {
+            return false;
+        };
+        if term.chars().count().abs_diff(query_length) > max_edits {
+            return false;
+        }
+
+        let query_chars: Vec<char> = self.lower.chars().collect();
+        let term_chars: Vec<char> = term.chars().collect();
+        damerau_levenshtein(&query_chars, &term_chars, max_edits).is_some()
+    }
+
+    /// Scores `info` against the query, or `None` when nothing matches.
+    /// `popularity` is the package's install percentile, 0-100.
+    pub fn score(&self, info: &ApiPackageInfo, popularity: f64) -> Option<SearchMatch> {
+        let mut scorer = Scorer::default();
+
+        scorer.name_like(SearchField::Name, canonical_name(info), None, self);
+        if info.token.as_deref().is_some_and(|token| token != info.name) {
+            scorer.name_like(SearchField::DisplayName, &info.name, None, self);
+        }
+        for alias in &info.aliases {
+            scorer.name_like(SearchField::Alias, alias, Some(alias), self);
+        }
+        for old_name in &info.old_names {
+            scorer.name_like(SearchField::OldName, old_name, Some(old_name), self);
+        }
+
+        // Typos are only forgiven when nothing matched the name directly
+        if scorer.best.is_none() {
+            scorer.fuzzy_name(canonical_name(info), self);
+        }
+
+        scorer.description(&info.description, self);
+        scorer.metadata(SearchField::Tap, &info.tap, self);
+        if let Some(domain) = info.homepage.as_deref().and_then(homepage_domain) {
+            scorer.metadata(SearchField::Homepage, domain, self);
+        }
+
+        let (best_score, kind) = scorer.best?;
+        let score = best_score + scorer.description_score + popularity_boost(popularity);
+
+        Some(SearchMatch {
+            score: round_score(score),
+            kind,
+            matched_fields: scorer.fields,
+            highlights: scorer.highlights,
+        })
+    }
+
+    /// The match for a package the query names outright through a redirect
+    /// the other fields do not carry, e.g. `homebrew/core/wget`
+    pub fn redirected(&self, popularity: f64) -> SearchMatch {
+        SearchMatch {
+            score: round_score(EXACT_SCORE + popularity_boost(popularity)),
+            kind: MatchKind::Exact,
+            matched_fields: vec![SearchField::Name],
+            highlights: Vec::new(),
+        }
+    }
+}
+
+fn popularity_boost(popularity: f64) -> f64 {
+    popularity.clamp(0.0, 100.0) / 100.0 * POPULARITY_BOOST
+}
+
+fn round_score(score: f64) -> f64 {
+    (score * 10.0).round() / 10.0
+}
+
+#[derive(Default)]
+struct Scorer {
+    /// Best name-like match; the description adds on top
+    best: Option<(f64, MatchKind)>,
+    description_score: f64,
+    fields: Vec<SearchField>,
+    highlights: Vec<SearchHighlight>,
+}
+
+impl Scorer {
+    fn record(&mut self, field: SearchField, value: Option<&String>, score: f64, kind: MatchKind, span: (usize, usize)) {
+        if self.best.is_none_or(|(best, _)| score > best) {
+            self.best = Some((score, kind));
+        }
+        if !self.fields.contains(&field) {
+            self.fields.push(field);
+        }
+        self.highlights.push(SearchHighlight {
+            field,
+            value: value.cloned(),
+            start: span.0,
+            length: span.1,
+        });
+    }
+
+    fn name_like(&mut self, field: SearchField, text: &str, value: Option<&String>, query: &SearchQuery) {
+        let lower = text.to_lowercase();
+        let query_length = query.lower.chars().count();
+        let weight = field.weight();
+
+        let (score, kind, span) = if lower == query.lower {
+            (EXACT_SCORE, MatchKind::Exact, (0, query_length))
+        } else if lower.starts_with(&query.lower) {
+            let extra = (lower.chars().count() - query_length) as f64;
+            let penalty = (extra * PREFIX_LENGTH_PENALTY).min(MAX_PREFIX_LENGTH_PENALTY);
+            (PREFIX_SCORE - penalty, MatchKind::Prefix, (0, query_length))
+        } else if let Some((start, _)) = words(&lower).find(|(_, word)| *word == query.lower) {
+            (TOKEN_SCORE, MatchKind::Token, (start, query_length))
+        } else if let Some((start, _)) = words(&lower).find(|(_, word)| word.starts_with(&query.lower)) {
+            (TOKEN_PREFIX_SCORE, MatchKind::Token, (start, query_length))
+        } else if let Some(byte_start) = lower.find(&query.lower) {
+            (SUBSTRING_SCORE, MatchKind::Substring, (lower[..byte_start].chars().count(), query_length))
+        } else {
+            return;
+        };
+
+        self.record(field, value, score * weight, kind, span);
+    }
+
+    /// Damerau-Levenshtein against the name and each of its words
+    fn fuzzy_name(&mut self, name: &str, query: &SearchQuery) {
+        let query_chars: Vec<char> = query.lower.chars().collect();
+        let Some(max_edits) = max_edits(query_chars.len()) else {
+            return;
+        };
+
+        let lower = name.to_lowercase();
+        let candidates = std::iter::once((0, lower.as_str())).chain(words(&lower));
+        let best = candidates
+            .filter_map(|(start, candidate)| {
+                let candidate_chars: Vec<char> = candidate.chars().collect();
+                if candidate_chars.len().abs_diff(query_chars.len()) > max_edits {
+                    return None;
+                }
+                let edits = damerau_levenshtein(&query_chars, &candidate_chars, max_edits)?;
+                Some((edits, start, candidate_chars.len()))
+            })
+            .min_by_key(|(edits, _, _)| *edits);
+
+        if let Some((edits, start, length)) = best {
+            let score = FUZZY_SCORE - FUZZY_PENALTY_PER_EDIT * edits as f64;
+            self.record(SearchField::Name, None, score, MatchKind::Fuzzy, (start, length));
+        }
+    }
+
+    fn description(&mut self, description: &str, query: &SearchQuery) {
+        let lower = description.to_lowercase();
+        let mut score = 0.0;
+        let mut matched_tokens = 0;
+
+        for token in query.tokens.iter().filter(|token| token.chars().count() >= 2) {
+            let token_length = token.chars().count();
+            let matched = if let Some((start, _)) = words(&lower).find(|(_, word)| word == token) {
+                Some((DESCRIPTION_WORD_SCORE, start))
+            } else {
+                words(&lower)
+                    .find(|(_, word)| word.starts_with(token.as_str()))
+                    .map(|(start, _)| (DESCRIPTION_WORD_PREFIX_SCORE, start))
+            };
+
+            if let Some((token_score, start)) = matched {
+                score += token_score;
+                matched_tokens += 1;
+                self.highlights.push(SearchHighlight {
+                    field: SearchField::Description,
+                    value: None,
+                    start,
+                    length: token_length,
+                });
+            }
+        }
+
+        if matched_tokens == 0 {
+            return;
+        }
+        if matched_tokens == query.tokens.len() && matched_tokens > 1 {
+            score += DESCRIPTION_ALL_TOKENS_BONUS;
+        }
+
+        self.description_score = score.min(DESCRIPTION_MAX_SCORE);
+        self.fields.push(SearchField::Description);
+        if self.best.is_none() {
+            self.best = Some((0.0, MatchKind::Description));
+        }
+    }
+
+    /// Whole-word matches in the tap or homepage domain; they count like
+    /// description words and share its cap
+    fn metadata(&mut self, field: SearchField, text: &str, query: &SearchQuery) {
+        let lower = text.to_lowercase();
+        let matched: Vec<(usize, usize)> = query
+            .tokens
+            .iter()
+            .filter(|token| field != SearchField::Tap || token.as_str() != OFFICIAL_TAP_OWNER)
+            .filter_map(|token| {
+                words(&lower)
+                    .find(|(_, word)| word == token)
+                    .map(|(start, _)| (start, token.chars().count()))
+            })
+            .collect();
+
+        if matched.is_empty() {
+            return;
+        }
+
+        let score = METADATA_WORD_SCORE * matched.len() as f64;
+        self.description_score = (self.description_score + score).min(DESCRIPTION_MAX_SCORE);
+        self.fields.push(field);
+        self.highlights.extend(matched.into_iter().map(|(start, length)| SearchHighlight {
+            field,
+            value: Some(text.to_string()),
+            start,
+            length,
+        }));
+        if self.best.is_none() {
+            self.best = Some((0.0, MatchKind::Metadata));
+        }
+    }
+}
+
+/// The host of a homepage URL without `www.`, e.g. `git-scm.com`
+pub fn homepage_domain(homepage: &str) -> Option<&str> {
+    let without_scheme = homepage.split_once("://").map_or(homepage, |(_, rest)| rest);
+    let host = without_scheme.split(['/', '?', '#']).next()?;
+    let host = host.rsplit_once('@').map_or(host, |(_, host)| host);
+    let host = host.split(':').next()?;
+    let host = host.strip_prefix("www.").unwrap_or(host);
+
+    (!host.is_empty()).then_some(host)
+}
+
+/// Typos forgiven for a query of `length` characters: one from four
+/// characters and two from eight
+fn max_edits(length: usize) -> Option<usize> {
+    match length {
+        0..=3 => None,
+        4..=7 => Some(1),
+        _ => Some(2),
+    }
+}
+
+/// Words of `text` split on anything that is not alphanumeric, with their
+/// offsets in characters
+pub fn words(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut offset = 0;
+    text.split(|c: char| !c.is_alphanumeric()).filter_map(move |word| {
+        let start = offset;
+        offset += word.chars().count() + 1;
+        (!word.is_empty()).then_some((start, word))
+    })
+}
+
+/// Optimal string alignment distance (Damerau-Levenshtein without repeated
+/// edits of a substring), or `None` once it exceeds `max_edits`
+fn damerau_levenshtein(a: &[char], b: &[char], max_edits: usize) -> Option<usize> {
+    let width = b.len() + 1;
+    let mut previous_previous: Vec<usize> = vec![0; width];
+    let mut previous: Vec<usize> = (0..width).collect();
+    let mut current: Vec<usize> = vec![0; width];
+
+    for i in 1..=a.len() {
+        current[0] = i;
+        let mut row_min = current[0];
+
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (previous[j] + 1).min(current[j - 1] + 1).min(previous[j - 1] + cost);
+
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(previous_previous[j - 2] + 1);
+            }
+
+            current[j] = distance;
+            row_min = row_min.min(distance);
+        }
+
+        if row_min > max_edits {
+            return None;
+        }
+        std::mem::swap(&mut previous_previous, &mut previous);
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    Some(previous[b.len()]).filter(|distance| *distance <= max_edits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::api_client::PackageParser;
+    use crate::services::platform::HostPlatform;
+    use serde_json::json;
+
+    fn formula(value: serde_json::Value) -> ApiPackageInfo {
+        PackageParser::parse_formula_to_package_info(serde_json::from_value(value).unwrap(), HostPlatform::current())
+    }
+
+    fn named(name: &str) -> ApiPackageInfo {
+        formula(json!({"name": name, "full_name": name}))
+    }
+
+    /// Names of the matching packages, best first, the way search sorts them
+    fn ranked(query: &str, packages: &[(ApiPackageInfo, f64)]) -> Vec<String> {
+        let query = SearchQuery::new(query).unwrap();
+        let mut matches: Vec<(f64, &str)> = packages
+            .iter()
+            .filter_map(|(info, popularity)| {
+                query.score(info, *popularity).map(|search_match| (search_match.score, info.name.as_str()))
+            })
+            .collect();
+        matches.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+        matches.into_iter().map(|(_, name)| name.to_string()).collect()
+    }
+
+    #[test]
+    fn exact_name_beats_earlier_substring_matches() {
+        let packages = [(named("cgit"), 0.0), (named("bgit"), 0.0), (named("git-lfs"), 0.0), (named("git"), 0.0)];
+
+        assert_eq!(ranked("git", &packages), vec!["git", "git-lfs", "bgit", "cgit"]);
+    }
+
+    #[test]
+    fn ranks_exact_then_prefix_then_alias_then_description() {
+        let packages = [
+            (
+                formula(json!({
+                    "name": "curl",
+                    "full_name": "curl",
+                    "desc": "Get a file from an HTTP, HTTPS or FTP server, like wget"
+                })),
+                0.0,
+            ),
+            (formula(json!({"name": "gnu-fetch", "full_name": "gnu-fetch", "aliases": ["wget-classic"]})), 0.0),
+            (named("wget2"), 0.0),
+            (named("wget"), 0.0),
+        ];
+        let query = SearchQuery::new("wget").unwrap();
+        let kinds: Vec<MatchKind> = packages.iter().map(|(info, _)| query.score(info, 0.0).unwrap().kind).collect();
+
+        assert_eq!(ranked("wget", &packages), vec!["wget", "wget2", "gnu-fetch", "curl"]);
+        assert_eq!(kinds, vec![MatchKind::Description, MatchKind::Prefix, MatchKind::Prefix, MatchKind::Exact]);
+    }
+
+    #[test]
+    fn forgives_one_typo() {
+        let query = SearchQuery::new("ripgrpe").unwrap();
+        let search_match = query.score(&named("ripgrep"), 0.0).unwrap();
+
+        assert_eq!(search_match.kind, MatchKind::Fuzzy);
+        assert_eq!(search_match.score, FUZZY_SCORE - FUZZY_PENALTY_PER_EDIT);
+        assert!(query.is_fuzzy_match("ripgrep"));
+        // Short queries must match as typed
+        assert!(SearchQuery::new("gti").unwrap().score(&named("git"), 0.0).is_none());
+    }
+
+    #[test]
+    fn popularity_only_breaks_ties() {
+        let packages = [(named("git"), 0.0), (named("git-lfs"), 100.0), (named("git-svn"), 50.0), (named("gitg"), 100.0)];
+
+        // `git-lfs` and `git-svn` tie on the match and are ordered by
+        // popularity; neither overtakes the exact name
+        assert_eq!(ranked("git", &packages), vec!["git", "gitg", "git-lfs", "git-svn"]);
+    }
+
+    #[test]
+    fn reports_matched_fields() {
+        let info = formula(json!({
+            "name": "git",
+            "full_name": "git",
+            "desc": "Distributed revision control system",
+            "homepage": "https://git-scm.com",
+            "oldnames": ["git-core"]
+        }));
+
+        let search_match = SearchQuery::new("git").unwrap().score(&info, 0.0).unwrap();
+        assert_eq!(search_match.matched_fields, vec![SearchField::Name, SearchField::OldName, SearchField::Homepage]);
+        assert_eq!(
+            search_match.highlights[0],
+            SearchHighlight {
+                field: SearchField::Name,
+                value: None,
+                start: 0,
+                length: 3,
+            }
+        );
+
+        let search_match = SearchQuery::new("revision control").unwrap().score(&info, 0.0).unwrap();
+        assert_eq!(search_match.kind, MatchKind::Description);
+        assert_eq!(search_match.matched_fields, vec![SearchField::Description]);
+        assert_eq!(
+            search_match.highlights.iter().map(|highlight| highlight.start).collect::<Vec<_>>(),
+            vec![12, 21]
+        );
+    }
+}
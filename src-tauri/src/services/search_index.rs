@@ -0,0 +1,449 @@
+use crate::error::BrewDeckError;
+use crate::services::api_client::ApiPackageInfo;
+use crate::services::brew_client::PackageType;
+use crate::services::catalog_index::canonical_name;
+use crate::services::search::{homepage_domain, words, SearchQuery, OFFICIAL_TAP_OWNER};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::ops::Bound;
+use std::path::Path;
+use tracing::{debug, warn};
+
+/// Bumped whenever the indexed terms change, so older files are rebuilt
+const SEARCH_INDEX_VERSION: u32 = 1;
+
+/// The terms one package is indexed under
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct IndexedTerms {
+    /// Name, display name, aliases and old names, whole and word by word
+    names: BTreeSet<String>,
+    /// Description, tap and homepage domain words
+    text: BTreeSet<String>,
+}
+
+impl IndexedTerms {
+    fn of(info: &ApiPackageInfo) -> Self {
+        let mut terms = Self::default();
+
+        let names = std::iter::once(canonical_name(info))
+            .chain(info.token.is_some().then_some(info.name.as_str()))
+            .chain(info.aliases.iter().map(String::as_str))
+            .chain(info.old_names.iter().map(String::as_str));
+        for name in names {
+            add_terms(&mut terms.names, name, true);
+        }
+
+        add_terms(&mut terms.text, &info.description, false);
+
+        // Every official tap is `homebrew/...`; that prefix would match the
+        // whole catalog, so only third-party taps are indexed whole
+        let tap = info.tap.trim().to_lowercase();
+        terms.text.extend(
+            words(&tap)
+                .filter(|(_, word)| *word != OFFICIAL_TAP_OWNER)
+                .map(|(_, word)| word.to_string()),
+        );
+        if !tap.is_empty() && !tap.starts_with("homebrew/") {
+            terms.text.insert(tap);
+        }
+        if let Some(domain) = info.homepage.as_deref().and_then(homepage_domain) {
+            add_terms(&mut terms.text, domain, true);
+        }
+
+        terms
+    }
+}
+
+/// Hash of the fields `IndexedTerms::of` reads, so an unchanged package is
+/// recognised without tokenizing it again
+fn fingerprint(info: &ApiPackageInfo) -> u64 {
+    let mut hasher = Sha256::new();
+
+    let fields = [info.name.as_str(), info.token.as_deref().unwrap_or_default()]
+        .into_iter()
+        .chain(info.aliases.iter().map(String::as_str))
+        .chain(std::iter::once("\u{1}"))
+        .chain(info.old_names.iter().map(String::as_str))
+        .chain([
+            "\u{1}",
+            info.description.as_str(),
+            info.tap.as_str(),
+            info.homepage.as_deref().unwrap_or_default(),
+        ]);
+    for field in fields {
+        hasher.update(field.as_bytes());
+        hasher.update([0]);
+    }
+
+    let digest = hasher.finalize();
+    u64::from_le_bytes(digest[..8].try_into().expect("SHA-256 digests are 32 bytes"))
+}
+
+/// An indexed package: its terms, to unindex it when it changes, and the
+/// fingerprint of the fields they came from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexedDocument {
+    fingerprint: u64,
+    terms: IndexedTerms,
+}
+
+/// Adds the words of `value`, and with `whole` set the full lowercased value
+/// too, e.g. `git-lfs` besides `git` and `lfs`
+fn add_terms(terms: &mut BTreeSet<String>, value: &str, whole: bool) {
+    let lower = value.trim().to_lowercase();
+
+    terms.extend(words(&lower).map(|(_, word)| word.to_string()));
+    if whole && !lower.is_empty() {
+        terms.insert(lower);
+    }
+}
+
+/// What a sync changed, by package
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SearchIndexChanges {
+    pub added: usize,
+    pub updated: usize,
+    pub removed: usize,
+}
+
+impl SearchIndexChanges {
+    pub fn is_empty(&self) -> bool {
+        self.added == 0 && self.updated == 0 && self.removed == 0
+    }
+}
+
+/// Inverted index from terms to the packages that contain them, so a search
+/// only scores packages sharing a term prefix with the query instead of the
+/// whole catalog.
+///
+/// Persisted next to the catalog and synced package by package when a new
+/// catalog is parsed; packages whose fingerprint is unchanged are not
+/// re-tokenized.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchIndex {
+    version: u32,
+    package_type: PackageType,
+    /// Name-like term -> canonical names
+    names: BTreeMap<String, BTreeSet<String>>,
+    /// Description, tap and homepage term -> canonical names
+    text: BTreeMap<String, BTreeSet<String>>,
+    /// Canonical name -> its indexed terms and fingerprint
+    documents: HashMap<String, IndexedDocument>,
+}
+
+impl SearchIndex {
+    pub fn new(package_type: PackageType) -> Self {
+        Self {
+            version: SEARCH_INDEX_VERSION,
+            package_type,
+            names: BTreeMap::new(),
+            text: BTreeMap::new(),
+            documents: HashMap::new(),
+        }
+    }
+
+    /// Reads a persisted index; `None` when it is missing, unreadable or
+    /// from another index version, in which case it is rebuilt
+    pub fn load(path: &Path, package_type: PackageType) -> Option<Self> {
+        let bytes = std::fs::read(path).ok()?;
+
+        match serde_json::from_slice::<Self>(&bytes) {
+            Ok(index) if index.version == SEARCH_INDEX_VERSION && index.package_type == package_type => Some(index),
+            Ok(_) => {
+                debug!("Rebuilding outdated search index {}", path.display());
+                None
+            }
+            Err(e) => {
+                warn!("Ignoring unreadable search index {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), BrewDeckError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let temp_path = path.with_extension("json.tmp");
+        std::fs::write(&temp_path, serde_json::to_vec(self)?)?;
+        std::fs::rename(&temp_path, path)?;
+
+        debug!("Saved {} search index to {}", self.package_type, path.display());
+        Ok(())
+    }
+
+    /// Brings the index in line with `packages`: new packages are added,
+    /// packages whose indexed fields changed are re-indexed and packages no longer in
+    /// the catalog are dropped.
+    pub fn sync(&mut self, packages: &[ApiPackageInfo]) -> SearchIndexChanges {
+        let mut changes = SearchIndexChanges::default();
+        let mut current = HashSet::with_capacity(packages.len());
+
+        for info in packages {
+            let name = canonical_name(info);
+            current.insert(name);
+
+            let fingerprint = fingerprint(info);
+            let previous = self.documents.get(name).map(|document| document.fingerprint);
+            if previous == Some(fingerprint) {
+                continue;
+            }
+
+            let terms = IndexedTerms::of(info);
+            self.remove(name);
+            self.insert(name, &terms);
+            self.documents.insert(name.to_string(), IndexedDocument { fingerprint, terms });
+            if previous.is_some() {
+                changes.updated += 1;
+            } else {
+                changes.added += 1;
+            }
+        }
+
+        let removed: Vec<String> = self
+            .documents
+            .keys()
+            .filter(|name| !current.contains(name.as_str()))
+            .cloned()
+            .collect();
+        for name in removed {
+            self.remove(&name);
+            changes.removed += 1;
+        }
+
+        changes
+    }
+
+    fn insert(&mut self, name: &str, terms: &IndexedTerms) {
+        for term in &terms.names {
+            self.names.entry(term.clone()).or_default().insert(name.to_string());
+        }
+        for term in &terms.text {
+            self.text.entry(term.clone()).or_default().insert(name.to_string());
+        }
+    }
+
+    fn remove(&mut self, name: &str) {
+        let Some(IndexedDocument { terms, .. }) = self.documents.remove(name) else {
+            return;
+        };
+
+        for (postings, terms) in [(&mut self.names, &terms.names), (&mut self.text, &terms.text)] {
+            for term in terms {
+                if let Some(packages) = postings.get_mut(term) {
+                    packages.remove(name);
+                    if packages.is_empty() {
+                        postings.remove(term);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Canonical names of the packages worth scoring for `query`: those with
+    /// a term starting with one of its words, a name containing it, or a
+    /// name within its typo tolerance. Every package `SearchQuery::score`
+    /// matches is among them.
+    pub fn candidates(&self, query: &SearchQuery) -> BTreeSet<&str> {
+        let mut candidates = BTreeSet::new();
+
+        let prefix_matches = query
+            .tokens()
+            .iter()
+            .flat_map(|token| with_prefix(&self.names, token).chain(with_prefix(&self.text, token)))
+            .chain(with_prefix(&self.names, query.text()));
+        for packages in prefix_matches {
+            candidates.extend(packages.iter().map(String::as_str));
+        }
+
+        // Substrings and typos cannot be looked up by prefix; the name terms
+        // are few enough to scan
+        let text = query.text();
+        let scan_substrings = text.chars().count() >= 2;
+        for (term, packages) in &self.names {
+            if (scan_substrings && term.contains(text)) || query.is_fuzzy_match(term) {
+                candidates.extend(packages.iter().map(String::as_str));
+            }
+        }
+
+        candidates
+    }
+}
+
+/// Postings of every term starting with `prefix`, via a range scan
+fn with_prefix<'a: 'p, 'p>(
+    postings: &'a BTreeMap<String, BTreeSet<String>>,
+    prefix: &'p str,
+) -> impl Iterator<Item = &'a BTreeSet<String>> + 'p {
+    postings
+        .range::<str, _>((Bound::Included(prefix), Bound::Unbounded))
+        .take_while(move |(term, _)| term.starts_with(prefix))
+        .map(|(_, packages)| packages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::api_client::ApiAnalytics;
+    use std::time::{Duration, Instant};
+
+    fn package(name: &str, description: &str) -> ApiPackageInfo {
+        ApiPackageInfo {
+            name: name.to_string(),
+            token: None,
+            full_name: name.to_string(),
+            aliases: Vec::new(),
+            old_names: Vec::new(),
+            version: "1.0".to_string(),
+            revision: 0,
+            description: description.to_string(),
+            homepage: Some(format!("https://{name}.example.org")),
+            dependencies: Vec::new(),
+            build_dependencies: Vec::new(),
+            uses_from_macos: Vec::new(),
+            conflicts: Vec::new(),
+            caveats: None,
+            analytics: ApiAnalytics::default(),
+            bottle: None,
+            unmet_requirements: Vec::new(),
+            deprecated: false,
+            disabled: false,
+            auto_updates: None,
+            warnings: Vec::new(),
+            license: None,
+            tap: "homebrew/core".to_string(),
+        }
+    }
+
+    /// A catalog about the size of homebrew/core, with made-up hyphenated names
+    fn synthetic_catalog(count: usize) -> Vec<ApiPackageInfo> {
+        const SYLLABLES: &[&str] = &["lib", "git", "py", "net", "zip", "ssl", "core", "x", "tool", "dev", "lua", "qt"];
+
+        (0..count)
+            .map(|i| {
+                let first = SYLLABLES[i % SYLLABLES.len()];
+                let second = SYLLABLES[(i / SYLLABLES.len()) % SYLLABLES.len()];
+                let name = format!("{first}{second}-{i}");
+                package(&name, &format!("Utility number {i} for {first} and {second} files"))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sync_reports_added_updated_and_removed() {
+        let mut index = SearchIndex::new(PackageType::Formula);
+        let mut packages = vec![package("wget", "Internet file retriever"), package("curl", "Get a file from a URL")];
+
+        let changes = index.sync(&packages);
+        assert_eq!((changes.added, changes.updated, changes.removed), (2, 0, 0));
+
+        assert!(index.sync(&packages).is_empty());
+
+        packages[0].description = "Internet file downloader".to_string();
+        packages.pop();
+        let changes = index.sync(&packages);
+        assert_eq!((changes.added, changes.updated, changes.removed), (0, 1, 1));
+
+        let query = SearchQuery::new("downloader").unwrap();
+        assert_eq!(index.candidates(&query), BTreeSet::from(["wget"]));
+        assert!(index.candidates(&SearchQuery::new("retriever").unwrap()).is_empty());
+        assert!(index.candidates(&SearchQuery::new("curl").unwrap()).is_empty());
+    }
+
+    #[test]
+    fn fingerprint_covers_indexed_fields_only() {
+        let original = package("wget", "Internet file retriever");
+
+        let mut moved = original.clone();
+        moved.old_names.push("wget2".to_string());
+        assert_ne!(fingerprint(&original), fingerprint(&moved));
+
+        // An alias and an old name of the same value are different terms
+        let mut aliased = original.clone();
+        aliased.aliases.push("wget2".to_string());
+        assert_ne!(fingerprint(&aliased), fingerprint(&moved));
+
+        let mut bumped = original.clone();
+        bumped.version = "2.0".to_string();
+        assert_eq!(fingerprint(&original), fingerprint(&bumped));
+    }
+
+    #[test]
+    fn candidates_include_prefix_substring_and_typo_matches() {
+        let mut index = SearchIndex::new(PackageType::Formula);
+        index.sync(&[
+            package("git-lfs", "Git extension for versioning large files"),
+            package("wget", "Internet file retriever"),
+            package("ripgrep", "Search tool like grep"),
+        ]);
+
+        assert_eq!(index.candidates(&SearchQuery::new("lfs").unwrap()), BTreeSet::from(["git-lfs"]));
+        assert_eq!(index.candidates(&SearchQuery::new("pgre").unwrap()), BTreeSet::from(["ripgrep"]));
+        assert_eq!(index.candidates(&SearchQuery::new("wegt").unwrap()), BTreeSet::from(["wget"]));
+    }
+
+    #[test]
+    fn candidates_cover_every_scored_match() {
+        let mut git_lfs = package("git-lfs", "Git extension for versioning large files");
+        git_lfs.aliases.push("lfs".to_string());
+        let mut font = package("font-fira-code", "Monospaced font with programming ligatures");
+        font.tap = "homebrew/cask-fonts".to_string();
+        font.homepage = Some("https://github.com/tonsky/FiraCode".to_string());
+        let packages = vec![
+            git_lfs,
+            font,
+            package("wget", "Internet file retriever"),
+            package("ripgrep", "Search tool like grep"),
+        ];
+
+        let mut index = SearchIndex::new(PackageType::Formula);
+        index.sync(&packages);
+
+        let queries = ["git", "rsion", "larg", "homebrew", "core", "fonts", "github", "example", "grep", "ripgrpe", "f"];
+        for query in queries {
+            let query = SearchQuery::new(query).unwrap();
+            let scored: BTreeSet<&str> = packages
+                .iter()
+                .filter(|info| query.score(info, 0.0).is_some())
+                .map(canonical_name)
+                .collect();
+
+            assert!(
+                scored.is_subset(&index.candidates(&query)),
+                "{:?} matched {scored:?} but only {:?} were candidates",
+                query.text(),
+                index.candidates(&query)
+            );
+        }
+    }
+
+    /// Run with `cargo test --release -- --ignored`; wall-clock budgets are
+    /// too noisy for a shared CI runner
+    #[test]
+    #[ignore]
+    fn candidates_stay_within_single_digit_milliseconds() {
+        let mut index = SearchIndex::new(PackageType::Formula);
+        index.sync(&synthetic_catalog(8_000));
+
+        // Queries long enough to scan every name term for typos
+        let queries: Vec<SearchQuery> = ["libgit", "toolpy-4", "sslcore", "gtipy"]
+            .iter()
+            .map(|query| SearchQuery::new(query).unwrap())
+            .collect();
+        const ROUNDS: u32 = 5;
+
+        let start = Instant::now();
+        for _ in 0..ROUNDS {
+            for query in &queries {
+                assert!(!index.candidates(query).is_empty());
+            }
+        }
+        let average = start.elapsed() / (ROUNDS * queries.len() as u32);
+
+        // Unoptimized builds are several times slower
+        let budget = if cfg!(debug_assertions) { Duration::from_millis(50) } else { Duration::from_millis(10) };
+        assert!(average < budget, "candidates took {average:?} on average");
+    }
+}
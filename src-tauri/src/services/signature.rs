@@ -0,0 +1,311 @@
+use crate::error::BrewDeckError;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use rsa::pkcs8::DecodePublicKey;
+use rsa::{Pss, RsaPublicKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha512};
+use std::path::{Path, PathBuf};
+use tracing::{debug, info};
+
+/// Key id brew signs the API catalogs with
+pub const HOMEBREW_KEY_ID: &str = "homebrew-1";
+
+/// PS512 uses a salt as long as the SHA-512 digest
+const PSS_SALT_LENGTH: usize = 64;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SignatureConfig {
+    /// Fetch `formula.jws.json` / `cask.jws.json` and reject payloads that do not verify
+    pub enabled: bool,
+    pub key_id: String,
+    /// PEM-encoded public key; takes precedence over `public_key_path`
+    pub public_key_pem: Option<String>,
+    /// Defaults to the key brew ships at `Library/Homebrew/api/<key_id>.pem`
+    pub public_key_path: Option<String>,
+}
+
+impl Default for SignatureConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            key_id: HOMEBREW_KEY_ID.to_string(),
+            public_key_pem: None,
+            public_key_path: None,
+        }
+    }
+}
+
+impl SignatureConfig {
+    /// Where the settings are kept unless configured otherwise
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("BrewDeck").join("signature.json"))
+    }
+
+    /// Reads the settings file; a missing file means verification is off
+    pub fn load(path: &Path) -> Result<Self, BrewDeckError> {
+        match std::fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| {
+                BrewDeckError::InvalidConfiguration(format!("Invalid signature settings {}: {}", path.display(), e))
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), BrewDeckError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let temp_path = path.with_extension("json.tmp");
+        std::fs::write(&temp_path, serde_json::to_vec_pretty(self)?)?;
+        std::fs::rename(&temp_path, path)?;
+
+        info!("Saved signature settings to {}", path.display());
+        Ok(())
+    }
+
+    /// Where the public key is read from when no PEM is configured inline
+    pub fn key_path(&self, brew_repository: &Path) -> PathBuf {
+        match &self.public_key_path {
+            Some(path) => PathBuf::from(path),
+            None => brew_repository
+                .join("Library/Homebrew/api")
+                .join(format!("{}.pem", self.key_id)),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct JwsEnvelope {
+    /// The catalog JSON itself, unencoded since the header sets `b64: false`
+    payload: String,
+    signatures: Vec<JwsSignature>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JwsSignature {
+    protected: String,
+    #[serde(default)]
+    header: JwsUnprotectedHeader,
+    signature: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct JwsUnprotectedHeader {
+    kid: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JwsProtectedHeader {
+    alg: String,
+    b64: Option<bool>,
+}
+
+/// Verifies the JWS (JSON serialization) envelopes formulae.brew.sh publishes
+/// next to each catalog, the same way `brew` does: PS512 over
+/// `<protected>.<payload>` with an unencoded payload.
+pub struct JwsVerifier {
+    key_id: String,
+    public_key: RsaPublicKey,
+}
+
+impl JwsVerifier {
+    pub fn from_pem(key_id: &str, pem: &str) -> Result<Self, BrewDeckError> {
+        let public_key = RsaPublicKey::from_public_key_pem(pem.trim())
+            .map_err(|e| BrewDeckError::InvalidConfiguration(format!("Invalid public key for {key_id}: {e}")))?;
+
+        Ok(Self {
+            key_id: key_id.to_string(),
+            public_key,
+        })
+    }
+
+    /// Builds the verifier for `config`, or `None` when verification is off
+    pub fn from_config(config: &SignatureConfig, brew_repository: &Path) -> Result<Option<Self>, BrewDeckError> {
+        if !config.enabled {
+            return Ok(None);
+        }
+
+        let pem = match &config.public_key_pem {
+            Some(pem) => pem.clone(),
+            None => {
+                let path = config.key_path(brew_repository);
+                std::fs::read_to_string(&path).map_err(|e| {
+                    BrewDeckError::InvalidConfiguration(format!(
+                        "Cannot read public key {}: {}",
+                        path.display(),
+                        e
+                    ))
+                })?
+            }
+        };
+
+        Self::from_pem(&config.key_id, &pem).map(Some)
+    }
+
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    /// Checks the envelope's signature and returns the signed payload
+    pub fn verify(&self, envelope: &[u8]) -> Result<Vec<u8>, BrewDeckError> {
+        let envelope: JwsEnvelope = serde_json::from_slice(envelope)
+            .map_err(|e| BrewDeckError::SignatureVerificationFailed(format!("Malformed JWS envelope: {e}")))?;
+
+        let signature = envelope
+            .signatures
+            .iter()
+            .find(|signature| signature.header.kid.as_deref() == Some(self.key_id.as_str()))
+            .ok_or_else(|| {
+                BrewDeckError::SignatureVerificationFailed(format!("No signature with key id {}", self.key_id))
+            })?;
+
+        let protected_header: JwsProtectedHeader = decode(&signature.protected, "protected header")
+            .and_then(|bytes| {
+                serde_json::from_slice(&bytes).map_err(|e| {
+                    BrewDeckError::SignatureVerificationFailed(format!("Malformed protected header: {e}"))
+                })
+            })?;
+
+        if protected_header.alg != "PS512" || protected_header.b64 != Some(false) {
+            return Err(BrewDeckError::SignatureVerificationFailed(format!(
+                "Unsupported JWS header: alg {}, b64 {:?}",
+                protected_header.alg, protected_header.b64
+            )));
+        }
+
+        let signing_input = format!("{}.{}", signature.protected, envelope.payload);
+        let digest = Sha512::digest(signing_input.as_bytes());
+        let signature_bytes = decode(&signature.signature, "signature")?;
+
+        self.public_key
+            .verify(Pss::new_with_salt::<Sha512>(PSS_SALT_LENGTH), &digest, &signature_bytes)
+            .map_err(|_| {
+                BrewDeckError::SignatureVerificationFailed(format!(
+                    "Signature by {} does not match the payload",
+                    self.key_id
+                ))
+            })?;
+
+        debug!("Verified JWS payload signed by {}", self.key_id);
+        Ok(envelope.payload.into_bytes())
+    }
+}
+
+/// JWS uses unpadded base64url, but tolerate padding
+fn decode(value: &str, what: &str) -> Result<Vec<u8>, BrewDeckError> {
+    URL_SAFE_NO_PAD
+        .decode(value.trim_end_matches('='))
+        .map_err(|e| BrewDeckError::SignatureVerificationFailed(format!("Invalid base64 in {what}: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rsa::pkcs8::{EncodePublicKey, LineEnding};
+    use rsa::RsaPrivateKey;
+    use serde_json::json;
+    use std::sync::OnceLock;
+
+    const PAYLOAD: &str = r#"[{"name":"wget","versions":{"stable":"1.24.5"}}]"#;
+    const PROTECTED_HEADER: &str = r#"{"alg":"PS512","b64":false,"crit":["b64"]}"#;
+
+    /// A throwaway key; generating one is slow in debug builds, so it is shared
+    fn test_key() -> &'static RsaPrivateKey {
+        static KEY: OnceLock<RsaPrivateKey> = OnceLock::new();
+        KEY.get_or_init(|| RsaPrivateKey::new(&mut rand::thread_rng(), 2048).unwrap())
+    }
+
+    fn verifier() -> JwsVerifier {
+        let pem = test_key().to_public_key().to_public_key_pem(LineEnding::LF).unwrap();
+        JwsVerifier::from_pem(HOMEBREW_KEY_ID, &pem).unwrap()
+    }
+
+    fn sign(protected_header: &str, payload: &str) -> serde_json::Value {
+        let protected = URL_SAFE_NO_PAD.encode(protected_header);
+        let digest = Sha512::digest(format!("{protected}.{payload}").as_bytes());
+        let signature = test_key()
+            .sign_with_rng(&mut rand::thread_rng(), Pss::new_with_salt::<Sha512>(PSS_SALT_LENGTH), &digest)
+            .unwrap();
+
+        json!({
+            "payload": payload,
+            "signatures": [{
+                "protected": protected,
+                "header": {"kid": HOMEBREW_KEY_ID},
+                "signature": URL_SAFE_NO_PAD.encode(signature),
+            }],
+        })
+    }
+
+    fn assert_rejected(envelope: &serde_json::Value) {
+        match verifier().verify(envelope.to_string().as_bytes()) {
+            Err(BrewDeckError::SignatureVerificationFailed(_)) => {}
+            other => panic!("expected a verification failure, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn accepts_valid_signature() {
+        let envelope = sign(PROTECTED_HEADER, PAYLOAD);
+
+        let payload = verifier().verify(envelope.to_string().as_bytes()).unwrap();
+        assert_eq!(payload, PAYLOAD.as_bytes());
+    }
+
+    #[test]
+    fn rejects_tampered_payload() {
+        let mut envelope = sign(PROTECTED_HEADER, PAYLOAD);
+        envelope["payload"] = json!(PAYLOAD.replace("1.24.5", "1.24.6"));
+
+        assert_rejected(&envelope);
+    }
+
+    #[test]
+    fn rejects_tampered_protected_header() {
+        let mut envelope = sign(PROTECTED_HEADER, PAYLOAD);
+        envelope["signatures"][0]["protected"] =
+            json!(URL_SAFE_NO_PAD.encode(r#"{"alg":"PS512","b64":false,"crit":["b64"],"kid":"x"}"#));
+
+        assert_rejected(&envelope);
+    }
+
+    #[test]
+    fn rejects_unknown_key_id() {
+        let mut envelope = sign(PROTECTED_HEADER, PAYLOAD);
+        envelope["signatures"][0]["header"]["kid"] = json!("homebrew-2");
+
+        assert_rejected(&envelope);
+    }
+
+    #[test]
+    fn saves_and_loads_config() {
+        let path = std::env::temp_dir().join(format!("brewdeck-signature-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        assert!(!SignatureConfig::load(&path).unwrap().enabled);
+
+        let config = SignatureConfig {
+            enabled: true,
+            key_id: HOMEBREW_KEY_ID.to_string(),
+            public_key_pem: Some(test_key().to_public_key().to_public_key_pem(LineEnding::LF).unwrap()),
+            public_key_path: None,
+        };
+        config.save(&path).unwrap();
+        let loaded = SignatureConfig::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(loaded.enabled);
+        assert_eq!(loaded.key_id, HOMEBREW_KEY_ID);
+        assert!(JwsVerifier::from_config(&loaded, Path::new("/nonexistent")).unwrap().is_some());
+    }
+
+    #[test]
+    fn rejects_unsupported_algorithm() {
+        let envelope = sign(r#"{"alg":"RS512","b64":false,"crit":["b64"]}"#, PAYLOAD);
+
+        assert_rejected(&envelope);
+    }
+}